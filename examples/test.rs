@@ -36,12 +36,12 @@ fn go() -> Result<(), Error> {
         let mut buf = try!(queues[0].create_buffer(1024));
         let mut buf_host = vec!(1,2,3,4,5);
 
-        try!(queues[0].write_buffer(&mut buf, &buf_host[..]));
+        try!(queues[0].write_buffer(&mut buf, &buf_host[..], &[]));
 
         let mut k = try!(program.create_kernel("zero"));
         try!(k.bind_mut(0, &mut buf));
-        try!(queues[0].run(&mut k, (1,1,1), (2,1,1)));
-        try!(queues[0].read_buffer(&buf, &mut buf_host[..]));
+        try!(queues[0].run(&mut k, &NDRange::new_1d(2).local(&[1]), &[]));
+        try!(queues[0].read_buffer(&buf, &mut buf_host[..], &[]));
         println!("{:?}", buf_host);
     }
 
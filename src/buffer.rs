@@ -0,0 +1,149 @@
+use error::Error;
+use mem::{Mem, MemLike, MemFlags};
+use command_queue::{CommandQueue, ReadLock, WriteLock, MapMode, MappedBuffer};
+use event::{Event, EventLike};
+
+use std::marker::PhantomData;
+use std::mem::size_of;
+
+/// Typed OpenCL buffer
+///
+/// A `Buffer<T>` is a `Mem` that remembers its element type and length.
+/// Reads and writes are checked against that length before anything is
+/// enqueued, so a mismatched host slice is an error rather than an overrun.
+/// The underlying `Mem` is available through `MemLike::mem()`.
+pub struct Buffer<T: Sized> {
+    mem: Mem,
+    len: usize,
+    marker: PhantomData<T>,
+}
+
+impl<T: Sized> Buffer<T> {
+    /// Create a buffer holding `len` elements of type `T`
+    pub fn new(queue: &CommandQueue,
+               len: usize) -> Result<Buffer<T>, Error> {
+        let size_bytes = match len.checked_mul(size_of::<T>()) {
+            Some(size_bytes) => size_bytes,
+            None => return Err(Error::InvalidBufferSize),
+        };
+        let mem = try!(queue.create_buffer(size_bytes));
+        Ok(Buffer{ mem: mem, len: len, marker: PhantomData })
+    }
+
+    /// Create a buffer with the same length and contents as a slice
+    pub fn from_slice(queue: &CommandQueue,
+                      slice: &[T]) -> Result<Buffer<T>, Error> {
+        let mut buf = try!(Buffer::new(queue, slice.len()));
+        try!(buf.write(queue, slice, &[]));
+        Ok(buf)
+    }
+
+    /// Returns the number of elements in the buffer
+    pub fn len(self: &Self) -> usize {
+        self.len
+    }
+
+    fn check_len(self: &Self, len: usize) -> Result<(), Error> {
+        if len != self.len {
+            return Err(Error::BufferSizeMismatch(self.len, len));
+        }
+        Ok(())
+    }
+
+    /// Writes a slice to the buffer and returns a lock to wait until it finishes
+    pub fn write<'a>(self: &mut Self,
+                     queue: &CommandQueue,
+                     slice: &'a [T],
+                     wait_for: &[&dyn EventLike]) -> Result<ReadLock<'a, T>, Error> {
+        try!(self.check_len(slice.len()));
+        queue.write_buffer(&mut self.mem, slice, wait_for)
+    }
+
+    /// Reads the buffer into a slice and returns a lock to wait until it finishes
+    pub fn read<'a>(self: &Self,
+                    queue: &CommandQueue,
+                    slice: &'a mut [T],
+                    wait_for: &[&dyn EventLike]) -> Result<WriteLock<'a, T>, Error> {
+        try!(self.check_len(slice.len()));
+        queue.read_buffer(&self.mem, slice, wait_for)
+    }
+
+    fn check_range(self: &Self, offset: usize, len: usize) -> Result<(), Error> {
+        match offset.checked_add(len) {
+            Some(end) if end <= self.len => Ok(()),
+            Some(end) => Err(Error::BufferOutOfBounds(end, self.len)),
+            None => Err(Error::BufferOutOfBounds(usize::max_value(), self.len)),
+        }
+    }
+
+    /// Writes a slice to the buffer starting at element `offset`
+    pub fn write_at<'a>(self: &mut Self,
+                        queue: &CommandQueue,
+                        offset: usize,
+                        slice: &'a [T],
+                        wait_for: &[&dyn EventLike]) -> Result<ReadLock<'a, T>, Error> {
+        try!(self.check_range(offset, slice.len()));
+        queue.write_buffer_at(&mut self.mem, offset, slice, wait_for)
+    }
+
+    /// Reads from the buffer starting at element `offset` into a slice
+    pub fn read_at<'a>(self: &Self,
+                       queue: &CommandQueue,
+                       offset: usize,
+                       slice: &'a mut [T],
+                       wait_for: &[&dyn EventLike]) -> Result<WriteLock<'a, T>, Error> {
+        try!(self.check_range(offset, slice.len()));
+        queue.read_buffer_at(&self.mem, offset, slice, wait_for)
+    }
+
+    /// Create a typed sub-buffer covering `len` elements starting at element `offset`
+    pub fn sub_buffer(self: &Self,
+                      offset: usize,
+                      len: usize,
+                      flags: MemFlags) -> Result<Buffer<T>, Error> {
+        try!(self.check_range(offset, len));
+        let mem = try!(self.mem.sub_buffer(offset * size_of::<T>(),
+                                           len * size_of::<T>(),
+                                           flags));
+        Ok(Buffer{ mem: mem, len: len, marker: PhantomData })
+    }
+
+    /// Begin copying the whole of another buffer of the same length into this one
+    pub fn copy_from(self: &mut Self,
+                     queue: &CommandQueue,
+                     source: &Buffer<T>,
+                     wait_for: &[&dyn EventLike]) -> Result<Event, Error> {
+        try!(self.check_len(source.len));
+        let size_bytes = self.len * size_of::<T>();
+        queue.copy_buffer(&source.mem, &mut self.mem, 0, 0, size_bytes, wait_for)
+    }
+
+    /// Begin filling the whole buffer with `value`
+    pub fn fill(self: &mut Self,
+                queue: &CommandQueue,
+                value: &T,
+                wait_for: &[&dyn EventLike]) -> Result<Event, Error> {
+        let size_bytes = self.len * size_of::<T>();
+        queue.fill_buffer(&mut self.mem, value, 0, size_bytes, wait_for)
+    }
+
+    /// Maps the whole buffer into host memory
+    pub fn map<'a, M: MapMode>(self: &'a mut Self,
+                               queue: &'a CommandQueue,
+                               mode: M,
+                               wait_for: &[&dyn EventLike]) -> Result<MappedBuffer<'a, T, M>, Error> {
+        queue.map_buffer(&mut self.mem, mode, self.len, wait_for)
+    }
+}
+
+impl<T: Sized> MemLike for Buffer<T> {
+    fn mem(self: &Self) -> &Mem {
+        &self.mem
+    }
+}
+
+impl<T: Sized> Clone for Buffer<T> {
+    fn clone(self: &Self) -> Self {
+        Buffer{ mem: self.mem.clone(), len: self.len, marker: PhantomData }
+    }
+}
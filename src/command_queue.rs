@@ -5,16 +5,39 @@ use low_level as ll;
 use error::Error;
 use device::Device;
 use context::Context;
-use event::{Event, EventStatus, EventLike};
+use event::{Event, EventStatus, EventLike, wait_list_ids, wait_list_ptr};
 use kernel::Kernel;
-use mem::Mem;
+use mem::{Mem, BufferBuilder};
+use image::ImageLike;
+use nd_range::NDRange;
 
 use std::ptr;
-use std::mem::{size_of, transmute};
+use std::mem::{size_of, transmute, forget};
+use std::ops::{Deref, DerefMut};
+use std::slice;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{self, Poll};
 
 /// An OpenCL command queue
 pub struct CommandQueue {
     pub id: ll::CommandQueue,
+    finish_on_drop: bool,
+}
+
+/// Builder for command queues with explicit properties
+///
+/// Queues are in-order with profiling disabled unless requested.  The
+/// queue size and on-device options need an OpenCL 2.0 device and the
+/// `opencl2` cargo feature.
+pub struct CommandQueueBuilder {
+    context: Context,
+    device: Device,
+    out_of_order: bool,
+    profiling: bool,
+    on_device: bool,
+    on_device_default: bool,
+    size: Option<u32>,
 }
 
 pub struct ReadLock<'a, T: Sized + 'a> {
@@ -29,14 +52,163 @@ pub struct WriteLock<'a, T: Sized + 'a> {
     evt: Event,
 }
 
+/// How a buffer is mapped into host memory
+///
+/// Implemented by `MapRead`, `MapWrite` and `MapWriteInvalidate`; the
+/// mode is part of the `MappedBuffer` type.
+pub trait MapMode {
+    fn flags(self: &Self) -> u64;
+}
+
+/// Map modes that let the host write to the mapped region
+pub trait MapWritable: MapMode { }
+
+/// The host only reads the mapped region
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MapRead;
+
+/// The host reads and writes the mapped region
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MapWrite;
+
+/// The host overwrites the mapped region; its prior contents are undefined
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MapWriteInvalidate;
+
+/// Buffer mapped into host memory
+///
+/// Dereferences to the mapped elements and unmaps the buffer when dropped.
+/// Mutable access is only available for writable map modes.
+pub struct MappedBuffer<'a, T: Sized + 'a, M: MapMode> {
+    queue: &'a CommandQueue,
+    mem: &'a mut Mem,
+    ptr: *mut T,
+    len: usize,
+    #[allow(dead_code)]
+    mode: M,
+}
+
+/// Buffer mapping that has been enqueued but may not have finished
+///
+/// Call `wait_mapped()` to get at the mapped memory.
+pub struct PendingMap<'a, T: Sized + 'a, M: MapMode> {
+    map: Option<MappedBuffer<'a, T, M>>,
+    evt: Event,
+}
+
+impl MapMode for MapRead {
+    fn flags(self: &Self) -> u64 {
+        1
+    }
+}
+
+impl MapMode for MapWrite {
+    fn flags(self: &Self) -> u64 {
+        1 | 2
+    }
+}
+
+impl MapMode for MapWriteInvalidate {
+    fn flags(self: &Self) -> u64 {
+        4
+    }
+}
+
+impl MapWritable for MapWrite { }
+
+impl MapWritable for MapWriteInvalidate { }
+
+impl<'a, T: Sized, M: MapMode> Deref for MappedBuffer<'a, T, M> {
+    type Target = [T];
+
+    fn deref(self: &Self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+impl<'a, T: Sized, M: MapWritable> DerefMut for MappedBuffer<'a, T, M> {
+    fn deref_mut(self: &mut Self) -> &mut [T] {
+        unsafe { slice::from_raw_parts_mut(self.ptr, self.len) }
+    }
+}
+
+impl<'a, T: Sized, M: MapMode> Drop for MappedBuffer<'a, T, M> {
+    fn drop(self: &mut Self) -> () {
+        let mut event_id: ll::Event = ptr::null_mut();
+        let unmapped = unsafe {
+            Error::check(ll::clEnqueueUnmapMemObject(self.queue.id,
+                                                     self.mem.id,
+                                                     self.ptr as *mut c_void,
+                                                     0,
+                                                     ptr::null(),
+                                                     &mut event_id))
+        };
+        // nothing to report an error to from here; if the unmap couldn't
+        // be enqueued, there is nothing to wait for
+        if unmapped.is_ok() {
+            wait_for_guard(&Event::new(event_id), "unmap buffer");
+        }
+    }
+}
+
+impl<'a, T: Sized, M: MapMode> PendingMap<'a, T, M> {
+    /// Waits for the map operation to finish and returns the mapping
+    pub fn wait_mapped(mut self: Self) -> Result<MappedBuffer<'a, T, M>, Error> {
+        try!(self.evt.wait());
+        Ok(self.map.take().unwrap())
+    }
+}
+
+impl<'a, T: Sized, M: MapMode> Drop for PendingMap<'a, T, M> {
+    fn drop(self: &mut Self) -> () {
+        // the unmap must not be enqueued before the map completes
+        if let Some(map) = self.map.take() {
+            if !wait_for_guard(&self.evt, "map for PendingMap") {
+                // an aborted map has nothing to unmap
+                forget(map);
+            }
+        }
+    }
+}
+
+impl<'a, T: Sized, M: MapMode> EventLike for PendingMap<'a, T, M> {
+    fn event(self: &Self) -> &Event {
+        &self.evt
+    }
+
+    fn status(self: &Self) -> Result<EventStatus, Error> {
+        self.evt.status()
+    }
+
+    fn wait(self: &Self) -> Result<(), Error> {
+        self.evt.wait()
+    }
+
+    fn then<F>(self: &Self, callback: F) -> Result<(), Error>
+        where F: FnOnce(EventStatus) + Send + 'static {
+        self.evt.then(callback)
+    }
+}
+
 impl<'a, T: Sized> Drop for ReadLock<'a, T> {
     fn drop(self: &mut Self) -> () {
-        self.evt.wait().ok().expect(
-            "error waiting to finish write for ReadLock");
+        wait_for_guard(&self.evt, "write for ReadLock");
+    }
+}
+
+impl<'a, T: Sized> Future for ReadLock<'a, T> {
+    type Output = Result<(), Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context) -> Poll<Result<(), Error>> {
+        Pin::new(&mut self.get_mut().evt).poll(cx)
     }
 }
 
 impl<'a, T: Sized> EventLike for ReadLock<'a, T> {
+    fn event(self: &Self) -> &Event {
+        &self.evt
+    }
+
     fn status(self: &Self) -> Result<EventStatus, Error> {
         self.evt.status()
     }
@@ -44,16 +216,32 @@ impl<'a, T: Sized> EventLike for ReadLock<'a, T> {
     fn wait(self: &Self) -> Result<(), Error> {
         self.evt.wait()
     }
+
+    fn then<F>(self: &Self, callback: F) -> Result<(), Error>
+        where F: FnOnce(EventStatus) + Send + 'static {
+        self.evt.then(callback)
+    }
 }
 
 impl<'a, T: Sized> Drop for WriteLock<'a, T> {
     fn drop(self: &mut Self) -> () {
-        self.evt.wait().ok().expect(
-            "error waiting to finish write for ReadLock");
+        wait_for_guard(&self.evt, "read for WriteLock");
+    }
+}
+
+impl<'a, T: Sized> Future for WriteLock<'a, T> {
+    type Output = Result<(), Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context) -> Poll<Result<(), Error>> {
+        Pin::new(&mut self.get_mut().evt).poll(cx)
     }
 }
 
 impl<'a, T: Sized> EventLike for WriteLock<'a, T> {
+    fn event(self: &Self) -> &Event {
+        &self.evt
+    }
+
     fn status(self: &Self) -> Result<EventStatus, Error> {
         self.evt.status()
     }
@@ -61,28 +249,247 @@ impl<'a, T: Sized> EventLike for WriteLock<'a, T> {
     fn wait(self: &Self) -> Result<(), Error> {
         self.evt.wait()
     }
+
+    fn then<F>(self: &Self, callback: F) -> Result<(), Error>
+        where F: FnOnce(EventStatus) + Send + 'static {
+        self.evt.then(callback)
+    }
 }
 
 unsafe impl Send for CommandQueue { }
 
-impl CommandQueue {
+/// Blocks until the command behind a guard has finished, successfully or not
+///
+/// Guards wait from `Drop`, where an error can't be returned.  A command
+/// that was aborted, e.g. because a `UserEvent` in its wait list failed, no
+/// longer touches host memory, so its error is ignored.  Only an event whose
+/// state can't be determined at all panics.  Returns true if the command
+/// completed successfully.
+fn wait_for_guard(evt: &Event, what: &str) -> bool {
+    if evt.wait().is_ok() {
+        return true;
+    }
+    match evt.status() {
+        Ok(EventStatus::Complete) => true,
+        Ok(EventStatus::Errored(_)) => false,
+        Ok(status) => panic!("error waiting to finish {}: still {:?}", what, status),
+        Err(e) => panic!("error waiting to finish {}: {:?}", what, e),
+    }
+}
+
+/// Returns one past the last byte touched by a rectangular transfer, or
+/// `None` if that overflows
+///
+/// `origin` and `region` follow the OpenCL convention of (bytes, rows,
+/// slices); a zero pitch means the tightly packed pitch for `region`.
+fn rect_extent(origin: (usize, usize, usize),
+               region: (usize, usize, usize),
+               pitch: (usize, usize)) -> Option<usize> {
+    if region.0 == 0 || region.1 == 0 || region.2 == 0 {
+        return Some(0);
+    }
+    let row_pitch = if pitch.0 == 0 { region.0 } else { pitch.0 };
+    let slice_pitch = if pitch.1 == 0 {
+        region.1.checked_mul(row_pitch)
+    } else {
+        Some(pitch.1)
+    };
+    let last_slice = origin.2.checked_add(region.2 - 1);
+    let last_row = origin.1.checked_add(region.1 - 1);
+    match (slice_pitch, last_slice, last_row) {
+        (Some(slice_pitch), Some(last_slice), Some(last_row)) => {
+            last_slice.checked_mul(slice_pitch)
+                .and_then(|start| last_row.checked_mul(row_pitch)
+                          .and_then(|rows| start.checked_add(rows)))
+                .and_then(|start| start.checked_add(origin.0))
+                .and_then(|start| start.checked_add(region.0))
+        },
+        _ => None,
+    }
+}
+
+/// Checks that a rectangular transfer stays within `size` bytes of host memory
+fn check_host_rect(size: usize,
+                   origin: (usize, usize, usize),
+                   region: (usize, usize, usize),
+                   pitch: (usize, usize)) -> Result<(), Error> {
+    match rect_extent(origin, region, pitch) {
+        Some(end) if end <= size => Ok(()),
+        end => Err(Error::BufferOutOfBounds(end.unwrap_or(usize::max_value()), size)),
+    }
+}
+
+/// Checks that a region starting at `origin` lies within an image
+fn check_image_region<I: ImageLike>(image: &I,
+                                    origin: (usize, usize, usize),
+                                    region: (usize, usize, usize)) -> Result<(), Error> {
+    let dims = image.dimensions();
+    let end = (origin.0.checked_add(region.0),
+               origin.1.checked_add(region.1),
+               origin.2.checked_add(region.2));
+    match end {
+        (Some(x), Some(y), Some(z)) if x <= dims.0 && y <= dims.1 && z <= dims.2 => Ok(()),
+        (x, y, z) => Err(Error::ImageRegionOutOfBounds(
+                (x.unwrap_or(usize::max_value()),
+                 y.unwrap_or(usize::max_value()),
+                 z.unwrap_or(usize::max_value())),
+                dims)),
+    }
+}
+
+/// Checks that a tightly packed image region fits in `host_size` bytes
+fn check_image_host_size<I: ImageLike>(image: &I,
+                                       region: (usize, usize, usize),
+                                       host_size: usize) -> Result<(), Error> {
+    let needed = region.0.checked_mul(region.1)
+        .and_then(|n| n.checked_mul(region.2))
+        .and_then(|n| n.checked_mul(image.format().element_size()));
+    match needed {
+        Some(needed) if needed <= host_size => Ok(()),
+        needed => Err(Error::BufferOutOfBounds(needed.unwrap_or(usize::max_value()), host_size)),
+    }
+}
+
+/// Returns the major OpenCL version reported by a device
+fn device_major_version(device: &Device) -> Result<u32, Error> {
+    // the version string looks like "OpenCL 1.2 <vendor-specific>"
+    let version = try!(device.version());
+    let major = version.split_whitespace()
+        .nth(1)
+        .and_then(|v| v.split('.').next())
+        .and_then(|v| v.parse().ok());
+    Ok(major.unwrap_or(1))
+}
+
+impl CommandQueueBuilder {
     pub fn new(context: Context,
-               device: Device) -> Result<CommandQueue, Error> {
-        let props: c_ulong = if try!(device.out_of_order_supported()) { 
-            1 
-        } else { 
-            0 
-        }; 
+               device: Device) -> CommandQueueBuilder {
+        CommandQueueBuilder{
+            context: context,
+            device: device,
+            out_of_order: false,
+            profiling: false,
+            on_device: false,
+            on_device_default: false,
+            size: None,
+        }
+    }
+
+    /// Sets whether commands may execute out of order
+    pub fn out_of_order(self: Self, out_of_order: bool) -> CommandQueueBuilder {
+        CommandQueueBuilder{ out_of_order: out_of_order, .. self }
+    }
+
+    /// Sets whether events record profiling timestamps
+    pub fn profiling(self: Self, profiling: bool) -> CommandQueueBuilder {
+        CommandQueueBuilder{ profiling: profiling, .. self }
+    }
+
+    /// Sets whether this is an on-device queue for device-side enqueue
+    /// (OpenCL 2.0); on-device queues must also be out of order
+    pub fn on_device(self: Self, on_device: bool) -> CommandQueueBuilder {
+        CommandQueueBuilder{ on_device: on_device, .. self }
+    }
+
+    /// Sets whether this is the device's default on-device queue (OpenCL 2.0)
+    pub fn on_device_default(self: Self, on_device_default: bool) -> CommandQueueBuilder {
+        CommandQueueBuilder{ on_device_default: on_device_default, .. self }
+    }
+
+    /// Sets the size in bytes of an on-device queue (OpenCL 2.0)
+    pub fn size(self: Self, size: u32) -> CommandQueueBuilder {
+        CommandQueueBuilder{ size: Some(size), .. self }
+    }
+
+    pub fn build(self: Self) -> Result<CommandQueue, Error> {
+        let mut props: u64 = 0;
+        if self.out_of_order {
+            props |= 1;
+        }
+        if self.profiling {
+            props |= 2;
+        }
+        if self.on_device {
+            props |= 4;
+        }
+        if self.on_device_default {
+            props |= 8;
+        }
+
+        let needs_cl2 = self.on_device || self.on_device_default || self.size.is_some();
+        let is_cl2 = cfg!(feature = "opencl2")
+            && try!(device_major_version(&self.device)) >= 2;
+        if needs_cl2 && !is_cl2 {
+            return Err(Error::InvalidQueueProperties);
+        }
+
+        let id = if is_cl2 {
+            try!(self.build_cl2(props))
+        } else {
+            unsafe {
+                let mut err: i32 = 0;
+                let id = ll::clCreateCommandQueue(self.context.id,
+                                                  self.device.id,
+                                                  props as c_ulong,
+                                                  &mut err);
+                try!(Error::check(err));
+                id
+            }
+        };
+        Ok(CommandQueue::from_id(id))
+    }
+
+    #[cfg(feature = "opencl2")]
+    fn build_cl2(self: &Self, props: u64) -> Result<ll::CommandQueue, Error> {
+        let mut properties: Vec<u64> = vec!(0x1093, props); // CL_QUEUE_PROPERTIES
+        if let Some(size) = self.size {
+            properties.push(0x1094); // CL_QUEUE_SIZE
+            properties.push(size as u64);
+        }
+        properties.push(0);
         let id = unsafe {
             let mut err: i32 = 0;
-            let id = ll::clCreateCommandQueue(context.id,
-                                              device.id,
-                                              props,
-                                              &mut err);
+            let id = ll::clCreateCommandQueueWithProperties(self.context.id,
+                                                            self.device.id,
+                                                            &properties[0],
+                                                            &mut err);
             try!(Error::check(err));
             id
         };
-        Ok(CommandQueue{ id: id })
+        Ok(id)
+    }
+
+    #[cfg(not(feature = "opencl2"))]
+    fn build_cl2(self: &Self, _props: u64) -> Result<ll::CommandQueue, Error> {
+        Err(Error::InvalidQueueProperties)
+    }
+}
+
+impl CommandQueue {
+    /// Wraps an already-retained queue id, with finish on drop off
+    pub fn from_id(id: ll::CommandQueue) -> CommandQueue {
+        CommandQueue{
+            id: id,
+            finish_on_drop: false,
+        }
+    }
+
+    /// Create an in-order command queue
+    ///
+    /// Use `CommandQueueBuilder` for out-of-order execution.
+    pub fn new(context: Context,
+               device: Device) -> Result<CommandQueue, Error> {
+        CommandQueueBuilder::new(context, device).build()
+    }
+
+    /// Create an in-order command queue with profiling enabled
+    ///
+    /// Events from this queue support `Event::profiling()`.
+    pub fn new_with_profiling(context: Context,
+                              device: Device) -> Result<CommandQueue, Error> {
+        CommandQueueBuilder::new(context, device)
+            .profiling(true)
+            .build()
     }
 
     fn get_info(self: &Self, param: u32) -> Result<*mut c_void, Error> {
@@ -133,9 +540,16 @@ impl CommandQueue {
                        buf: &mut Mem,
                        offset: usize,
                        size: usize,
-                       data: &[T]) -> Result<Event, Error> {
+                       data: &[T],
+                       wait_for: &[&dyn EventLike]) -> Result<Event, Error> {
+        if size == 0 {
+            // OpenCL rejects empty transfers
+            return self.marker_with_wait_list(wait_for);
+        }
         let mut event_id: ll::Event = ptr::null_mut();
-        let host_ptr = &data[0] as *const T;
+        let host_ptr = data.as_ptr();
+        let events = wait_list_ids(wait_for);
+        let events_ptr = wait_list_ptr(&events);
 
         unsafe {
             try!(Error::check(ll::clEnqueueWriteBuffer(self.id,
@@ -144,12 +558,12 @@ impl CommandQueue {
                                                        offset as size_t,
                                                        size as size_t,
                                                        transmute(host_ptr),
-                                                       0,
-                                                       ptr::null(),
+                                                       events.len() as u32,
+                                                       events_ptr,
                                                        &mut event_id)));
         }
 
-        let event = Event{id: event_id};
+        let event = Event::new(event_id);
         Ok(event)
     }
 
@@ -157,9 +571,16 @@ impl CommandQueue {
                                  buf: &Mem,
                                  offset: usize,
                                  size: usize,
-                                 data: &mut [T]) -> Result<Event, Error> {
+                                 data: &mut [T],
+                                 wait_for: &[&dyn EventLike]) -> Result<Event, Error> {
+        if size == 0 {
+            // OpenCL rejects empty transfers
+            return self.marker_with_wait_list(wait_for);
+        }
         let mut event_id: ll::Event = ptr::null_mut();
-        let host_ptr = &mut data[0] as *mut T;
+        let host_ptr = data.as_mut_ptr();
+        let events = wait_list_ids(wait_for);
+        let events_ptr = wait_list_ptr(&events);
 
         unsafe {
             try!(Error::check(ll::clEnqueueReadBuffer(self.id,
@@ -168,15 +589,111 @@ impl CommandQueue {
                                                       offset as size_t,
                                                       size as size_t,
                                                       transmute(host_ptr),
-                                                      0,
-                                                      ptr::null(),
+                                                      events.len() as u32,
+                                                      events_ptr,
                                                       &mut event_id)));
         }
 
-        let event = Event{id: event_id};
+        let event = Event::new(event_id);
         Ok(event)
     }
 
+    fn check_range(self: &Self,
+                   mem: &Mem,
+                   offset: usize,
+                   size: usize) -> Result<(), Error> {
+        let mem_size = try!(mem.size());
+        match offset.checked_add(size) {
+            Some(end) if end <= mem_size => Ok(()),
+            Some(end) => Err(Error::BufferOutOfBounds(end, mem_size)),
+            None => Err(Error::BufferOutOfBounds(usize::max_value(), mem_size)),
+        }
+    }
+
+    /// Checks that a rectangular transfer stays within a buffer
+    fn check_rect(self: &Self,
+                  mem: &Mem,
+                  origin: (usize, usize, usize),
+                  region: (usize, usize, usize),
+                  pitch: (usize, usize)) -> Result<(), Error> {
+        match rect_extent(origin, region, pitch) {
+            Some(end) => self.check_range(mem, 0, end),
+            None => Err(Error::BufferOutOfBounds(usize::max_value(), try!(mem.size()))),
+        }
+    }
+
+    /// Converts an element offset to bytes, failing if that overflows
+    fn offset_bytes<T: Sized>(self: &Self,
+                              mem: &Mem,
+                              offset: usize) -> Result<usize, Error> {
+        match size_of::<T>().checked_mul(offset) {
+            Some(bytes) => Ok(bytes),
+            None => Err(Error::BufferOutOfBounds(usize::max_value(), try!(mem.size()))),
+        }
+    }
+
+    fn map_buffer_raw<'a, T: Sized, M: MapMode>(self: &'a Self,
+                                                 mem: &'a mut Mem,
+                                                 blocking: bool,
+                                                 mode: M,
+                                                 len: usize,
+                                                 wait_for: &[&dyn EventLike]) -> Result<(MappedBuffer<'a, T, M>, Event), Error> {
+        let size_bytes = match len.checked_mul(size_of::<T>()) {
+            Some(size_bytes) => size_bytes,
+            None => return Err(Error::InvalidBufferSize),
+        };
+        try!(self.check_range(mem, 0, size_bytes));
+        let mut event_id: ll::Event = ptr::null_mut();
+        let events = wait_list_ids(wait_for);
+        let events_ptr = wait_list_ptr(&events);
+        let ptr = unsafe {
+            let mut err: i32 = 0;
+            let ptr = ll::clEnqueueMapBuffer(self.id,
+                                             mem.id,
+                                             if blocking { 1 } else { 0 },
+                                             mode.flags(),
+                                             0,
+                                             size_bytes as size_t,
+                                             events.len() as u32,
+                                             events_ptr,
+                                             &mut event_id,
+                                             &mut err);
+            try!(Error::check(err));
+            ptr
+        };
+        let map = MappedBuffer{
+            queue: self,
+            mem: mem,
+            ptr: ptr as *mut T,
+            len: len,
+            mode: mode,
+        };
+        Ok((map, Event::new(event_id)))
+    }
+
+    /// Maps the first `len` elements of a buffer into host memory
+    ///
+    /// Blocks until the mapping is available.  The buffer is unmapped when
+    /// the returned guard is dropped.
+    pub fn map_buffer<'a, T: Sized, M: MapMode>(self: &'a Self,
+                                                mem: &'a mut Mem,
+                                                mode: M,
+                                                len: usize,
+                                                wait_for: &[&dyn EventLike]) -> Result<MappedBuffer<'a, T, M>, Error> {
+        let (map, _) = try!(self.map_buffer_raw(mem, true, mode, len, wait_for));
+        Ok(map)
+    }
+
+    /// Begins mapping the first `len` elements of a buffer into host memory
+    pub fn begin_map_buffer<'a, T: Sized, M: MapMode>(self: &'a Self,
+                                                      mem: &'a mut Mem,
+                                                      mode: M,
+                                                      len: usize,
+                                                      wait_for: &[&dyn EventLike]) -> Result<PendingMap<'a, T, M>, Error> {
+        let (map, evt) = try!(self.map_buffer_raw(mem, false, mode, len, wait_for));
+        Ok(PendingMap{ map: Some(map), evt: evt })
+    }
+
     /// Create a memory buffer of the given size
     pub fn create_buffer(self: &Self, 
                                size_bytes: usize) -> Result<Mem, Error> {
@@ -185,56 +702,244 @@ impl CommandQueue {
         Ok(tr)
     }
 
+    /// Start building a buffer with custom memory flags in this queue's context
+    pub fn buffer_builder(self: &Self) -> Result<BufferBuilder, Error> {
+        Ok(BufferBuilder::new(try!(self.context())))
+    }
+
     /// Create a memory buffer from a slice of sized objects
     pub fn create_buffer_from_slice<T: Sized>(self: &Self,
                                     slice: &[T]) -> Result<Mem, Error> {
         let num_bytes = slice.len() * size_of::<T>();
         let mut buf = try!(self.create_buffer(num_bytes));
-        try!(self.write_buffer(&mut buf, slice));
+        try!(self.write_buffer(&mut buf, slice, &[]));
         Ok(buf)
     }
 
     /// Write a slice to the given buffer and does not wait for it to finish
     pub fn write_buffer_unscoped<T: Sized>(self: &Self,
                                   mem: &mut Mem,
-                                  slice: &[T]) -> Result<Event, Error> {
+                                  slice: &[T],
+                                  wait_for: &[&dyn EventLike]) -> Result<Event, Error> {
         let size_bytes = size_of::<T>()*slice.len();
         let evt = try!(self.write_buffer_raw(mem,
                                              0,
                                              size_bytes,
-                                             slice));
+                                             slice,
+                                             wait_for));
         Ok(evt)
     }
 
     /// Reads from the given buffer to a slice and does not wait for it to finish
     pub fn read_buffer_unscoped<T: Sized>(self: &Self,
                                                  mem: &Mem,
-                                                 slice: &mut [T]) -> Result<Event, Error> {
+                                                 slice: &mut [T],
+                                                 wait_for: &[&dyn EventLike]) -> Result<Event, Error> {
         let size_bytes = size_of::<T>()*slice.len();
         let evt = try!(self.read_buffer_raw(mem,
                                             0,
                                             size_bytes,
-                                            slice));
+                                            slice,
+                                            wait_for));
         Ok(evt)
     }
 
+    /// Writes a slice to a buffer starting at element `offset` and returns a lock
+    /// to wait until it finishes
+    pub fn write_buffer_at<'a, T: Sized + 'a>(self: &Self,
+                                              mem: &mut Mem,
+                                              offset: usize,
+                                              slice: &'a [T],
+                                              wait_for: &[&dyn EventLike]) -> Result<ReadLock<'a, T>, Error> {
+        let offset_bytes = try!(self.offset_bytes::<T>(mem, offset));
+        let size_bytes = size_of::<T>()*slice.len();
+        try!(self.check_range(mem, offset_bytes, size_bytes));
+        let evt = try!(self.write_buffer_raw(mem,
+                                             offset_bytes,
+                                             size_bytes,
+                                             slice,
+                                             wait_for));
+        Ok(ReadLock{ evt: evt, sl: slice })
+    }
+
+    /// Reads from a buffer starting at element `offset` to a slice and returns
+    /// a lock to wait until it finishes
+    pub fn read_buffer_at<'a, T: Sized>(self: &Self,
+                                        mem: &Mem,
+                                        offset: usize,
+                                        slice: &'a mut [T],
+                                        wait_for: &[&dyn EventLike]) -> Result<WriteLock<'a, T>, Error> {
+        let offset_bytes = try!(self.offset_bytes::<T>(mem, offset));
+        let size_bytes = size_of::<T>()*slice.len();
+        try!(self.check_range(mem, offset_bytes, size_bytes));
+        let evt = try!(self.read_buffer_raw(mem,
+                                            offset_bytes,
+                                            size_bytes,
+                                            slice,
+                                            wait_for));
+        Ok(WriteLock{ evt: evt, sl: slice })
+    }
+
+    /// Reads a region of an image into a tightly packed slice and returns a
+    /// lock to wait until it finishes
+    pub fn read_image<'a, I: ImageLike, T: Sized>(self: &Self,
+                                                  image: &I,
+                                                  origin: (usize, usize, usize),
+                                                  region: (usize, usize, usize),
+                                                  slice: &'a mut [T],
+                                                  wait_for: &[&dyn EventLike]) -> Result<WriteLock<'a, T>, Error> {
+        try!(check_image_region(image, origin, region));
+        try!(check_image_host_size(image, region, size_of::<T>()*slice.len()));
+
+        let mut event_id: ll::Event = ptr::null_mut();
+        let origin_vec = [origin.0, origin.1, origin.2];
+        let region_vec = [region.0, region.1, region.2];
+
+        let events = wait_list_ids(wait_for);
+        let events_ptr = wait_list_ptr(&events);
+
+        unsafe {
+            try!(Error::check(ll::clEnqueueReadImage(
+                        self.id,
+                        image.mem().id,
+                        0, // don't block
+                        &origin_vec[0],
+                        &region_vec[0],
+                        0,
+                        0,
+                        transmute(slice.as_mut_ptr()),
+                        events.len() as u32,
+                        events_ptr,
+                        &mut event_id)));
+        }
+
+        Ok(WriteLock{ evt: Event::new(event_id), sl: slice })
+    }
+
+    /// Writes a tightly packed slice to a region of an image and returns a
+    /// lock to wait until it finishes
+    pub fn write_image<'a, I: ImageLike, T: Sized>(self: &Self,
+                                                   image: &mut I,
+                                                   origin: (usize, usize, usize),
+                                                   region: (usize, usize, usize),
+                                                   slice: &'a [T],
+                                                   wait_for: &[&dyn EventLike]) -> Result<ReadLock<'a, T>, Error> {
+        try!(check_image_region(image, origin, region));
+        try!(check_image_host_size(image, region, size_of::<T>()*slice.len()));
+
+        let mut event_id: ll::Event = ptr::null_mut();
+        let origin_vec = [origin.0, origin.1, origin.2];
+        let region_vec = [region.0, region.1, region.2];
+
+        let events = wait_list_ids(wait_for);
+        let events_ptr = wait_list_ptr(&events);
+
+        unsafe {
+            try!(Error::check(ll::clEnqueueWriteImage(
+                        self.id,
+                        image.mem().id,
+                        0, // don't block
+                        &origin_vec[0],
+                        &region_vec[0],
+                        0,
+                        0,
+                        transmute(slice.as_ptr()),
+                        events.len() as u32,
+                        events_ptr,
+                        &mut event_id)));
+        }
+
+        Ok(ReadLock{ evt: Event::new(event_id), sl: slice })
+    }
+
+    /// Begin a copy of a region from one image to another of the same format
+    pub fn copy_image<I: ImageLike, J: ImageLike>(self: &Self,
+                                                  source: &I,
+                                                  destination: &mut J,
+                                                  source_origin: (usize, usize, usize),
+                                                  destination_origin: (usize, usize, usize),
+                                                  region: (usize, usize, usize),
+                                                  wait_for: &[&dyn EventLike]) -> Result<Event, Error> {
+        if source.format() != destination.format() {
+            return Err(Error::ImageFormatMismatch);
+        }
+        try!(check_image_region(source, source_origin, region));
+        try!(check_image_region(destination, destination_origin, region));
+
+        let mut event_id: ll::Event = ptr::null_mut();
+        let source_origin_vec = [source_origin.0, source_origin.1, source_origin.2];
+        let destination_origin_vec = [destination_origin.0, destination_origin.1, destination_origin.2];
+        let region_vec = [region.0, region.1, region.2];
+
+        let events = wait_list_ids(wait_for);
+        let events_ptr = wait_list_ptr(&events);
+
+        unsafe {
+            try!(Error::check(ll::clEnqueueCopyImage(
+                        self.id,
+                        source.mem().id,
+                        destination.mem().id,
+                        &source_origin_vec[0],
+                        &destination_origin_vec[0],
+                        &region_vec[0],
+                        events.len() as u32,
+                        events_ptr,
+                        &mut event_id)));
+        }
+
+        Ok(Event::new(event_id))
+    }
+
+    /// Begin filling a region of an image with a color
+    ///
+    /// `color` is four `f32`, `i32` or `u32` values, matching how the
+    /// image's channel type is read in a kernel.
+    pub fn fill_image<I: ImageLike, T: Sized>(self: &Self,
+                                              image: &mut I,
+                                              color: &[T; 4],
+                                              origin: (usize, usize, usize),
+                                              region: (usize, usize, usize),
+                                              wait_for: &[&dyn EventLike]) -> Result<Event, Error> {
+        if size_of::<T>() != 4 {
+            return Err(Error::InvalidValue);
+        }
+        try!(check_image_region(image, origin, region));
+
+        let mut event_id: ll::Event = ptr::null_mut();
+        let origin_vec = [origin.0, origin.1, origin.2];
+        let region_vec = [region.0, region.1, region.2];
+
+        let events = wait_list_ids(wait_for);
+        let events_ptr = wait_list_ptr(&events);
+
+        unsafe {
+            try!(Error::check(ll::clEnqueueFillImage(
+                        self.id,
+                        image.mem().id,
+                        transmute(color),
+                        &origin_vec[0],
+                        &region_vec[0],
+                        events.len() as u32,
+                        events_ptr,
+                        &mut event_id)));
+        }
+
+        Ok(Event::new(event_id))
+    }
+
     /// Begin a copy from a buffer to an image
     pub fn copy_buffer_to_image(self: &Self,
                                 source: &Mem,
                                 destination: &mut Mem,
                                 origin: (usize, usize, usize),
                                 region: (usize, usize, usize),
-                                wait_for: &[Event]) -> Result<Event, Error> {
+                                wait_for: &[&dyn EventLike]) -> Result<Event, Error> {
         let mut event_id: ll::Event = ptr::null_mut();
         let origin_vec = [origin.0, origin.1, origin.2];
         let region_vec = [region.0, region.1, region.2];
 
-        let events: Vec<ll::Event> = wait_for.iter().map(|e| e.id).collect();
-        let events_ptr: *const ll::Event = if events.len() > 0 {
-            &events[0]
-        } else {
-            ptr::null()
-        };
+        let events = wait_list_ids(wait_for);
+        let events_ptr = wait_list_ptr(&events);
 
         let origin_ptr: *const size_t = &origin_vec[0];
         let region_ptr: *const size_t = &region_vec[0];
@@ -252,9 +957,7 @@ impl CommandQueue {
                         &mut event_id)));
         }
 
-        Ok(Event{
-            id: event_id
-        })
+        Ok(Event::new(event_id))
     }
 
     /// Begin a copy from an image to a buffer
@@ -263,17 +966,13 @@ impl CommandQueue {
                                 destination: &mut Mem,
                                 origin: (usize, usize, usize),
                                 region: (usize, usize, usize),
-                                wait_for: &[Event]) -> Result<Event, Error> {
+                                wait_for: &[&dyn EventLike]) -> Result<Event, Error> {
         let mut event_id: ll::Event = ptr::null_mut();
         let origin_vec = [origin.0, origin.1, origin.2];
         let region_vec = [region.0, region.1, region.2];
 
-        let events: Vec<ll::Event> = wait_for.iter().map(|e| e.id).collect();
-        let events_ptr: *const ll::Event = if events.len() > 0 {
-            &events[0]
-        } else {
-            ptr::null()
-        };
+        let events = wait_list_ids(wait_for);
+        let events_ptr = wait_list_ptr(&events);
 
         let origin_ptr: *const size_t = &origin_vec[0];
         let region_ptr: *const size_t = &region_vec[0];
@@ -291,115 +990,375 @@ impl CommandQueue {
                         &mut event_id)));
         }
 
-        Ok(Event{
-            id: event_id
-        })
+        Ok(Event::new(event_id))
+    }
+
+    /// Reads a rectangular region of a buffer into a slice
+    ///
+    /// Origins and regions are (bytes, rows, slices) and pitches are
+    /// (row pitch, slice pitch) in bytes.  A zero pitch means the region is
+    /// tightly packed.  Returns a lock to wait until the read finishes.
+    pub fn read_buffer_rect<'a, T: Sized>(self: &Self,
+                                          mem: &Mem,
+                                          buffer_origin: (usize, usize, usize),
+                                          host_origin: (usize, usize, usize),
+                                          region: (usize, usize, usize),
+                                          buffer_pitch: (usize, usize),
+                                          host_pitch: (usize, usize),
+                                          slice: &'a mut [T],
+                                          wait_for: &[&dyn EventLike]) -> Result<WriteLock<'a, T>, Error> {
+        try!(check_host_rect(size_of::<T>()*slice.len(), host_origin, region, host_pitch));
+        try!(self.check_rect(mem, buffer_origin, region, buffer_pitch));
+
+        let mut event_id: ll::Event = ptr::null_mut();
+        let buffer_origin_vec = [buffer_origin.0, buffer_origin.1, buffer_origin.2];
+        let host_origin_vec = [host_origin.0, host_origin.1, host_origin.2];
+        let region_vec = [region.0, region.1, region.2];
+
+        let events = wait_list_ids(wait_for);
+        let events_ptr = wait_list_ptr(&events);
+
+        unsafe {
+            try!(Error::check(ll::clEnqueueReadBufferRect(
+                        self.id,
+                        mem.id,
+                        0, // don't block
+                        &buffer_origin_vec[0],
+                        &host_origin_vec[0],
+                        &region_vec[0],
+                        buffer_pitch.0,
+                        buffer_pitch.1,
+                        host_pitch.0,
+                        host_pitch.1,
+                        transmute(slice.as_mut_ptr()),
+                        events.len() as u32,
+                        events_ptr,
+                        &mut event_id)));
+        }
+
+        Ok(WriteLock{ evt: Event::new(event_id), sl: slice })
+    }
+
+    /// Writes a rectangular region of a slice into a buffer
+    ///
+    /// Origins, regions and pitches are as for `read_buffer_rect()`.  Returns
+    /// a lock to wait until the write finishes.
+    pub fn write_buffer_rect<'a, T: Sized>(self: &Self,
+                                           mem: &mut Mem,
+                                           buffer_origin: (usize, usize, usize),
+                                           host_origin: (usize, usize, usize),
+                                           region: (usize, usize, usize),
+                                           buffer_pitch: (usize, usize),
+                                           host_pitch: (usize, usize),
+                                           slice: &'a [T],
+                                           wait_for: &[&dyn EventLike]) -> Result<ReadLock<'a, T>, Error> {
+        try!(check_host_rect(size_of::<T>()*slice.len(), host_origin, region, host_pitch));
+        try!(self.check_rect(mem, buffer_origin, region, buffer_pitch));
+
+        let mut event_id: ll::Event = ptr::null_mut();
+        let buffer_origin_vec = [buffer_origin.0, buffer_origin.1, buffer_origin.2];
+        let host_origin_vec = [host_origin.0, host_origin.1, host_origin.2];
+        let region_vec = [region.0, region.1, region.2];
+
+        let events = wait_list_ids(wait_for);
+        let events_ptr = wait_list_ptr(&events);
+
+        unsafe {
+            try!(Error::check(ll::clEnqueueWriteBufferRect(
+                        self.id,
+                        mem.id,
+                        0, // don't block
+                        &buffer_origin_vec[0],
+                        &host_origin_vec[0],
+                        &region_vec[0],
+                        buffer_pitch.0,
+                        buffer_pitch.1,
+                        host_pitch.0,
+                        host_pitch.1,
+                        transmute(slice.as_ptr()),
+                        events.len() as u32,
+                        events_ptr,
+                        &mut event_id)));
+        }
+
+        Ok(ReadLock{ evt: Event::new(event_id), sl: slice })
+    }
+
+    /// Begin a copy of `size` bytes from one buffer to another
+    pub fn copy_buffer(self: &Self,
+                       source: &Mem,
+                       destination: &mut Mem,
+                       source_offset: usize,
+                       destination_offset: usize,
+                       size: usize,
+                       wait_for: &[&dyn EventLike]) -> Result<Event, Error> {
+        try!(self.check_range(source, source_offset, size));
+        try!(self.check_range(destination, destination_offset, size));
+
+        let mut event_id: ll::Event = ptr::null_mut();
+        let events = wait_list_ids(wait_for);
+        let events_ptr = wait_list_ptr(&events);
+
+        unsafe {
+            try!(Error::check(ll::clEnqueueCopyBuffer(
+                        self.id,
+                        source.id,
+                        destination.id,
+                        source_offset as size_t,
+                        destination_offset as size_t,
+                        size as size_t,
+                        events.len() as u32,
+                        events_ptr,
+                        &mut event_id)));
+        }
+
+        Ok(Event::new(event_id))
+    }
+
+    /// Begin filling `size` bytes of a buffer with copies of `pattern`
+    ///
+    /// `offset` and `size` are in bytes and must be multiples of the size of `T`.
+    pub fn fill_buffer<T: Sized>(self: &Self,
+                                 mem: &mut Mem,
+                                 pattern: &T,
+                                 offset: usize,
+                                 size: usize,
+                                 wait_for: &[&dyn EventLike]) -> Result<Event, Error> {
+        try!(self.check_range(mem, offset, size));
+
+        let mut event_id: ll::Event = ptr::null_mut();
+        let events = wait_list_ids(wait_for);
+        let events_ptr = wait_list_ptr(&events);
+
+        unsafe {
+            try!(Error::check(ll::clEnqueueFillBuffer(
+                        self.id,
+                        mem.id,
+                        transmute(pattern),
+                        size_of::<T>() as size_t,
+                        offset as size_t,
+                        size as size_t,
+                        events.len() as u32,
+                        events_ptr,
+                        &mut event_id)));
+        }
+
+        Ok(Event::new(event_id))
+    }
+
+    /// Begin a copy of a rectangular region from one buffer to another
+    ///
+    /// Origins, regions and pitches are as for `read_buffer_rect()`.
+    pub fn copy_buffer_rect(self: &Self,
+                            source: &Mem,
+                            destination: &mut Mem,
+                            source_origin: (usize, usize, usize),
+                            destination_origin: (usize, usize, usize),
+                            region: (usize, usize, usize),
+                            source_pitch: (usize, usize),
+                            destination_pitch: (usize, usize),
+                            wait_for: &[&dyn EventLike]) -> Result<Event, Error> {
+        try!(self.check_rect(source, source_origin, region, source_pitch));
+        try!(self.check_rect(destination, destination_origin, region, destination_pitch));
+
+        let mut event_id: ll::Event = ptr::null_mut();
+        let source_origin_vec = [source_origin.0, source_origin.1, source_origin.2];
+        let destination_origin_vec = [destination_origin.0, destination_origin.1, destination_origin.2];
+        let region_vec = [region.0, region.1, region.2];
+
+        let events = wait_list_ids(wait_for);
+        let events_ptr = wait_list_ptr(&events);
+
+        unsafe {
+            try!(Error::check(ll::clEnqueueCopyBufferRect(
+                        self.id,
+                        source.id,
+                        destination.id,
+                        &source_origin_vec[0],
+                        &destination_origin_vec[0],
+                        &region_vec[0],
+                        source_pitch.0,
+                        source_pitch.1,
+                        destination_pitch.0,
+                        destination_pitch.1,
+                        events.len() as u32,
+                        events_ptr,
+                        &mut event_id)));
+        }
+
+        Ok(Event::new(event_id))
     }
 
     /// Writes from a given slice to a buffer and returns a lock to wait until it finishes
     pub fn write_buffer<'a, 'b, T: Sized + 'a>(self: &Self,
                                   mem: &'b mut Mem,
-                                  slice: &'a [T]) -> Result<ReadLock<'a, T>, Error> {
+                                  slice: &'a [T],
+                                  wait_for: &[&dyn EventLike]) -> Result<ReadLock<'a, T>, Error> {
         let size_bytes = size_of::<T>()*slice.len();
         let evt = try!(self.write_buffer_raw(mem,
                                              0,
                                              size_bytes,
-                                             slice));
+                                             slice,
+                                             wait_for));
         Ok(ReadLock{ evt: evt, sl: slice })
     }
 
     /// Reads from a buffer to a slice and returns a lock to wait until the operation finishes
     pub fn read_buffer<'a, T: Sized>(self: &Self,
                                  mem: &Mem,
-                                 slice: &'a mut [T]) -> Result<WriteLock<'a, T>, Error> {
+                                 slice: &'a mut [T],
+                                 wait_for: &[&dyn EventLike]) -> Result<WriteLock<'a, T>, Error> {
         let size_bytes = size_of::<T>()*slice.len();
         let evt = try!(self.read_buffer_raw(mem,
                                             0,
                                             size_bytes,
-                                            slice));
+                                            slice,
+                                            wait_for));
         Ok(WriteLock{ evt: evt, sl: slice })
     }
 
-    fn next_mul(self: &Self, global: usize, local: usize) -> usize {
-        if global % local == 0 {
-            global
-        } else {
-            ((global/local)+1)*local
+    /// Submits all enqueued commands to the device without waiting for them
+    pub fn flush(self: &Self) -> Result<(), Error> {
+        unsafe {
+            try!(Error::check(ll::clFlush(self.id)));
+        }
+        Ok(())
+    }
+
+    /// Blocks until all enqueued commands have completed
+    pub fn finish(self: &Self) -> Result<(), Error> {
+        unsafe {
+            try!(Error::check(ll::clFinish(self.id)));
         }
+        Ok(())
     }
 
-    /// Enqueues a kernel
-    pub fn run( 
-                self: &Self, 
-                kernel: &mut Kernel, 
-                local_size: (usize, usize, usize),
-                global_size: (usize, usize, usize)) -> Result<Event, Error> {
-        let global_size_fixed = (self.next_mul(global_size.0, local_size.0),
-                                self.next_mul(global_size.1, local_size.1),
-                                self.next_mul(global_size.2, local_size.2));
+    /// Sets whether dropping this handle calls `finish()` first
+    ///
+    /// This only orders queue teardown after pending work; it does not make
+    /// unscoped reads and writes safe, since nothing stops their host
+    /// buffers from being dropped before the queue.  Clones inherit the
+    /// setting, but handles from `from_id()`, such as those returned by
+    /// `Event::command_queue()`, start with it off.
+    pub fn set_finish_on_drop(self: &mut Self, finish: bool) -> () {
+        self.finish_on_drop = finish;
+    }
 
-        let local_vec = vec!(local_size.0 as size_t, 
-                             local_size.1 as size_t, 
-                             local_size.2 as size_t);
-        let global_vec = vec!(global_size_fixed.0 as size_t, 
-                              global_size_fixed.1 as size_t,  
-                              global_size_fixed.2 as size_t);
+    /// Enqueues a marker that completes when all earlier commands have completed
+    pub fn marker(self: &Self) -> Result<Event, Error> {
+        let mut event_id: ll::Event = ptr::null_mut();
+        unsafe {
+            try!(Error::check(ll::clEnqueueMarker(self.id, &mut event_id)));
+        }
+        Ok(Event::new(event_id))
+    }
 
+    /// Enqueues a marker that completes when the given events have completed
+    ///
+    /// With an empty wait list this behaves like `marker()`.  Requires
+    /// OpenCL 1.2.
+    pub fn marker_with_wait_list(self: &Self,
+                                 wait_for: &[&dyn EventLike]) -> Result<Event, Error> {
         let mut event_id: ll::Event = ptr::null_mut();
+        let events = wait_list_ids(wait_for);
+        let events_ptr = wait_list_ptr(&events);
+
         unsafe {
-            let events_val = ptr::null_mut();
-            try!(Error::check(ll::clEnqueueNDRangeKernel(self.id,
-                                                         kernel.id,
-                                                         3,
-                                                         ptr::null(),
-                                                         &global_vec[0],
-                                                         &local_vec[0],
-                                                         0,
-                                                         events_val,
-                                                         &mut event_id)));
+            try!(Error::check(ll::clEnqueueMarkerWithWaitList(self.id,
+                                                              events.len() as u32,
+                                                              events_ptr,
+                                                              &mut event_id)));
         }
-        let event = Event{id: event_id};
+        Ok(Event::new(event_id))
+    }
 
-        Ok(event)
+    /// Enqueues a barrier: later commands wait until all earlier commands
+    /// have completed
+    ///
+    /// The returned event is a marker enqueued right after the barrier.
+    pub fn barrier(self: &Self) -> Result<Event, Error> {
+        unsafe {
+            try!(Error::check(ll::clEnqueueBarrier(self.id)));
+        }
+        self.marker()
     }
 
-    /// Enqueues a kernel after the given events
-    pub fn run_with_events( 
-                self: &Self, 
-                kernel: &mut Kernel, 
-                local_size: (usize, usize, usize),
-                global_size: (usize, usize, usize),
-                events_in: &[Event]) -> Result<Event, Error> {
-        let events: Vec<ll::Event> = events_in.iter().map(|e| e.id).collect();
-        let global_size_fixed = (self.next_mul(global_size.0, local_size.0),
-                                self.next_mul(global_size.1, local_size.1),
-                                self.next_mul(global_size.2, local_size.2));
-
-        let local_vec = vec!(local_size.0 as size_t, 
-                             local_size.1 as size_t, 
-                             local_size.2 as size_t);
-        let global_vec = vec!(global_size_fixed.0 as size_t, 
-                              global_size_fixed.1 as size_t,  
-                              global_size_fixed.2 as size_t);
+    /// Enqueues a barrier that holds back later commands until the given
+    /// events have completed
+    ///
+    /// With an empty wait list this behaves like `barrier()`.  Requires
+    /// OpenCL 1.2.
+    pub fn barrier_with_wait_list(self: &Self,
+                                  wait_for: &[&dyn EventLike]) -> Result<Event, Error> {
+        let mut event_id: ll::Event = ptr::null_mut();
+        let events = wait_list_ids(wait_for);
+        let events_ptr = wait_list_ptr(&events);
+
+        unsafe {
+            try!(Error::check(ll::clEnqueueBarrierWithWaitList(self.id,
+                                                               events.len() as u32,
+                                                               events_ptr,
+                                                               &mut event_id)));
+        }
+        Ok(Event::new(event_id))
+    }
+
+    /// Holds back later commands until the given events have completed
+    ///
+    /// The returned event is a marker enqueued right after the wait, so it
+    /// also waits for all earlier commands in the queue.
+    pub fn enqueue_wait_for_events(self: &Self,
+                                   wait_for: &[&dyn EventLike]) -> Result<Event, Error> {
+        if wait_for.len() == 0 {
+            return Err(Error::InvalidValue);
+        }
+        let events = wait_list_ids(wait_for);
+        unsafe {
+            try!(Error::check(ll::clEnqueueWaitForEvents(self.id,
+                                                         events.len() as u32,
+                                                         wait_list_ptr(&events))));
+        }
+        self.marker()
+    }
+
+    /// Enqueues a kernel over `range` after the given events
+    pub fn run(self: &Self,
+               kernel: &Kernel,
+               range: &NDRange,
+               wait_for: &[&dyn EventLike]) -> Result<Event, Error> {
+        try!(range.check());
+        let events = wait_list_ids(wait_for);
+
+        let global_vec: Vec<size_t> = range.global_size().iter()
+            .map(|&g| g as size_t).collect();
+        let offset_vec: Option<Vec<size_t>> = range.global_offset()
+            .map(|o| o.iter().map(|&x| x as size_t).collect());
+        let local_vec: Option<Vec<size_t>> = range.local_size()
+            .map(|l| l.iter().map(|&x| x as size_t).collect());
+
+        let offset_ptr: *const size_t = match offset_vec {
+            Some(ref o) => &o[0],
+            None => ptr::null(),
+        };
+        let local_ptr: *const size_t = match local_vec {
+            Some(ref l) => &l[0],
+            None => ptr::null(), // let the driver choose
+        };
+        let events_ptr = wait_list_ptr(&events);
 
         let mut event_id: ll::Event = ptr::null_mut();
         unsafe {
-            let events_val = if events.len() > 0 {
-                transmute(&events[0])
-            } else {
-                ptr::null_mut()
-            };
             try!(Error::check(ll::clEnqueueNDRangeKernel(self.id,
                                                          kernel.id,
-                                                         3,
-                                                         ptr::null(),
+                                                         range.dimensions(),
+                                                         offset_ptr,
                                                          &global_vec[0],
-                                                         &local_vec[0],
+                                                         local_ptr,
                                                          events.len() as u32,
-                                                         events_val,
+                                                         events_ptr,
                                                          &mut event_id)));
         }
-        let event = Event{id: event_id};
+        let event = Event::new(event_id);
 
         Ok(event)
     }
@@ -407,6 +1366,9 @@ impl CommandQueue {
 
 impl Drop for CommandQueue {
     fn drop(self: &mut Self) -> () {
+        if self.finish_on_drop {
+            self.finish().ok().expect("Error finishing command queue");
+        }
         unsafe { 
             Error::check(ll::clReleaseCommandQueue(self.id))
                 .ok().expect("Error releasing command queue");
@@ -420,7 +1382,47 @@ impl Clone for CommandQueue {
             Error::check(ll::clRetainCommandQueue(self.id))
                 .ok().expect("Error retaining command queue");
         }
-        CommandQueue{ id: self.id }
+        CommandQueue{ id: self.id, finish_on_drop: self.finish_on_drop }
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::{rect_extent, check_host_rect};
+    use error::Error;
+
+    #[test]
+    fn rect_extent_of_packed_region() {
+        // 4 bytes x 3 rows x 2 slices, tightly packed
+        assert_eq!(rect_extent((0, 0, 0), (4, 3, 2), (0, 0)), Some(24));
+        // last row starts at slice 2, row 3, byte 1 with 4-byte rows and 12-byte slices
+        assert_eq!(rect_extent((1, 1, 1), (4, 3, 2), (0, 0)), Some(24 + 12 + 1 + 4));
+    }
+
+    #[test]
+    fn rect_extent_with_pitches() {
+        // last byte is at slice 1, row 2, byte 3 with 16-byte rows and 64-byte slices
+        assert_eq!(rect_extent((0, 0, 0), (4, 3, 2), (16, 64)), Some(64 + 32 + 4));
+    }
+
+    #[test]
+    fn rect_extent_of_empty_region() {
+        assert_eq!(rect_extent((5, 5, 5), (0, 3, 2), (0, 0)), Some(0));
+    }
+
+    #[test]
+    fn rect_extent_overflow() {
+        let big = usize::max_value();
+        assert_eq!(rect_extent((0, 0, big), (1, 1, 2), (0, 0)), None);
+        assert_eq!(rect_extent((0, 0, 2), (1, 1, 1), (0, big)), None);
+    }
+
+    #[test]
+    fn host_rect_bounds() {
+        assert!(check_host_rect(24, (0, 0, 0), (4, 3, 2), (0, 0)).is_ok());
+        match check_host_rect(23, (0, 0, 0), (4, 3, 2), (0, 0)) {
+            Err(Error::BufferOutOfBounds(24, 23)) => {},
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
@@ -4,6 +4,8 @@ use self::libc::*;
 use low_level as ll;
 use error::Error;
 use device::Device;
+use mem::{MemFlags, MemObjectType};
+use image::ImageFormat;
 
 use std::ptr;
 use std::mem;
@@ -65,6 +67,42 @@ impl Context {
 
         Ok(device_ids.iter().map(|&id| Device{id:id}).collect())
     }
+
+    /// Returns the image formats supported for the given flags and image type
+    ///
+    /// Formats whose channel order or type proust does not know are skipped.
+    pub fn supported_image_formats(self: &Self,
+                                   flags: MemFlags,
+                                   image_type: MemObjectType) -> Result<Vec<ImageFormat>, Error> {
+        let num_formats = unsafe {
+            let mut tr: u32 = 0;
+            try!(Error::check(ll::clGetSupportedImageFormats(self.id,
+                                                             flags.bits as c_ulong,
+                                                             image_type.code(),
+                                                             0,
+                                                             ptr::null_mut(),
+                                                             &mut tr)));
+            tr
+        };
+        if num_formats == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut formats: Vec<ll::ImageFormat> = (0..num_formats)
+            .map(|_| ll::ImageFormat{ image_channel_order: 0, image_channel_data_type: 0 })
+            .collect();
+
+        unsafe {
+            try!(Error::check(ll::clGetSupportedImageFormats(self.id,
+                                                             flags.bits as c_ulong,
+                                                             image_type.code(),
+                                                             num_formats,
+                                                             &mut formats[0],
+                                                             ptr::null_mut())));
+        }
+
+        Ok(formats.iter().filter_map(ImageFormat::from_ll).collect())
+    }
 }
 
 impl Drop for Context {
@@ -260,6 +260,14 @@ impl Device {
         self.get_info_string(0x102E)
     }
 
+    pub fn image_max_buffer_size(self: &Self) -> Result<usize, Error> {
+        self.get_info_scalar(0x1040)
+    }
+
+    pub fn image_max_array_size(self: &Self) -> Result<usize, Error> {
+        self.get_info_scalar(0x1041)
+    }
+
     pub fn platform(self: &Self) -> Result<Platform, Error> {
         let id = try!(self.get_info_scalar::<ll::PlatformID>(0x1031));
         Ok(Platform{id:id})
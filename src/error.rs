@@ -10,11 +10,12 @@ pub enum Error {
     ProfilingInfoNotAvailable,
     MemCopyOverlap,
     ImageFormatMismatch,
-    ImageFormatNotSupported,
+    ImageFormatNotSupported(String),
     BuildProgramFailure(String),
     MapFailure,
     MisalignedSubBufferOffset,
     ExecStatusErrorForEventsInWaitList,
+    KernelArgInfoNotAvailable,
     InvalidValue,
     InvalidDeviceType,
     InvalidPlatform,
@@ -51,6 +52,21 @@ pub enum Error {
     InvalidGlobalWorkSize,
     InvalidProperty,
     UnknownError,
+
+    // errors detected by proust before calling into OpenCL
+
+    /// Host slice length does not match a typed buffer (buffer length, slice length)
+    BufferSizeMismatch(usize, usize),
+    /// Transfer runs past the end of a buffer (end of transfer, buffer size)
+    BufferOutOfBounds(usize, usize),
+    /// Region runs past the edge of an image (end of region, image dimensions)
+    ImageRegionOutOfBounds((usize, usize, usize), (usize, usize, usize)),
+    /// No kernel argument has the given name
+    InvalidArgName(String),
+    /// Bound value does not match the kernel argument (description)
+    ArgTypeMismatch(String),
+    /// Kernel uses more local memory than a device provides (used, available)
+    LocalMemExceeded(usize, usize),
 }
 
 impl Error {
@@ -67,11 +83,12 @@ impl Error {
             -7 => Err(ProfilingInfoNotAvailable),
             -8 => Err(MemCopyOverlap),
             -9 => Err(ImageFormatMismatch),
-            -10 => Err(ImageFormatNotSupported),
+            -10 => Err(ImageFormatNotSupported(String::from("No description available"))),
             -11 => Err(BuildProgramFailure(String::from("No log available"))),
             -12 => Err(MapFailure),
             -13 => Err(MisalignedSubBufferOffset),
             -14 => Err(ExecStatusErrorForEventsInWaitList),
+            -19 => Err(KernelArgInfoNotAvailable),
             -30 => Err(InvalidValue),
             -31 => Err(InvalidDeviceType),
             -32 => Err(InvalidPlatform),
@@ -3,28 +3,130 @@ use self::libc::*;
 
 use low_level as ll;
 use error::Error;
+use context::Context;
+use command_queue::CommandQueue;
 
 use std::ptr;
-use std::mem::{size_of, transmute};
+use std::mem::{size_of, transmute, zeroed};
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{self, Poll, Waker};
+use std::time::Duration;
+use std::sync::{mpsc, Arc, Mutex};
+use std::panic::{self, AssertUnwindSafe};
 
 /// OpenCL event
 pub struct Event {
     pub id: ll::Event,
+    waker: Option<WakerSlot>,
+}
+
+/// Waker for the task polling an event, shared with its completion callback
+type WakerSlot = Arc<Mutex<Option<Waker>>>;
+
+/// OpenCL event whose status is set by the host
+///
+/// Put `event()` in a wait list to hold commands back until `set_complete()`
+/// is called.  Dropping a `UserEvent` whose status was never set marks it as
+/// failed, so commands waiting on it are aborted instead of stalling the queue.
+pub struct UserEvent {
+    evt: Event,
+    status_set: bool,
+}
+
+/// Device timestamps for a command, from a queue with profiling enabled
+///
+/// Times are measured from an arbitrary device-specific origin, so only
+/// differences between them are meaningful.
+#[derive(Debug, Clone, Copy)]
+pub struct ProfilingInfo {
+    pub queued: Duration,
+    pub submitted: Duration,
+    pub start: Duration,
+    pub end: Duration,
 }
 
 /// OpenCL event status
+///
+/// `Errored` means the command terminated abnormally with the given error.
+#[derive(Debug)]
 pub enum EventStatus {
     Queued,
     Submitted,
     Running,
-    Complete
+    Complete,
+    Errored(Error),
+}
+
+/// Kind of command an event belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandType {
+    NDRangeKernel,
+    Task,
+    NativeKernel,
+    ReadBuffer,
+    WriteBuffer,
+    CopyBuffer,
+    ReadImage,
+    WriteImage,
+    CopyImage,
+    CopyImageToBuffer,
+    CopyBufferToImage,
+    MapBuffer,
+    MapImage,
+    UnmapMemObject,
+    Marker,
+    AcquireGLObjects,
+    ReleaseGLObjects,
+    ReadBufferRect,
+    WriteBufferRect,
+    CopyBufferRect,
+    User,
+    Barrier,
+    MigrateMemObjects,
+    FillBuffer,
+    FillImage,
+    Other(u32),
 }
 
 pub trait EventLike {
+    /// Returns the OpenCL event this waits on
+    fn event(self: &Self) -> &Event;
+
     fn status(self: &Self) -> Result<EventStatus, Error>;
     fn wait(self: &Self) -> Result<(), Error>;
-    // TODO: add callbacks
-    // fn then(self: &Self) -> Result<(), Error>;
+
+    /// Calls `callback` from an OpenCL thread when the command completes
+    ///
+    /// The callback receives `Complete`, or `Errored` if the command
+    /// terminated abnormally.  A panic in the callback is caught and
+    /// discarded, since it can't unwind through OpenCL.
+    fn then<F>(self: &Self, callback: F) -> Result<(), Error>
+        where F: FnOnce(EventStatus) + Send + 'static, Self: Sized;
+}
+
+/// Collects the OpenCL event ids of a wait list
+pub fn wait_list_ids(wait_for: &[&dyn EventLike]) -> Vec<ll::Event> {
+    wait_for.iter().map(|e| e.event().id).collect()
+}
+
+/// Pointer to a list of event ids as OpenCL expects it: null if empty
+pub fn wait_list_ptr(ids: &[ll::Event]) -> *const ll::Event {
+    if ids.len() > 0 {
+        ids.as_ptr()
+    } else {
+        ptr::null()
+    }
+}
+
+/// Callback receiving the raw execution status from OpenCL
+type Callback = Box<dyn FnOnce(i32) + Send>;
+
+extern fn event_callback(_event: ll::Event, status: i32, user_data: *mut c_void) -> () {
+    // takes back ownership of the box leaked in `Event::on_complete()`
+    let callback: Box<Callback> = unsafe { Box::from_raw(user_data as *mut Callback) };
+    // unwinding into OpenCL's thread would abort the process
+    let _ = panic::catch_unwind(AssertUnwindSafe(move || callback(status)));
 }
 
 unsafe impl Send for Event { }
@@ -36,22 +138,189 @@ impl EventStatus {
             1 => EventStatus::Running,
             2 => EventStatus::Submitted,
             3 => EventStatus::Queued,
-            _ => panic!("CL returned unknown event status code"),
+            _ => EventStatus::Errored(Error::check(code).err()
+                                      .unwrap_or(Error::UnknownError)),
         }
     }
 }
 
-impl EventLike for Event {
-    fn status(self: &Self) -> Result<EventStatus, Error> {
-        let mut code: i32 = 0;
+impl CommandType {
+    pub fn from_code(code: u32) -> CommandType {
+        use self::CommandType::*;
+        match code {
+            0x11F0 => NDRangeKernel,
+            0x11F1 => Task,
+            0x11F2 => NativeKernel,
+            0x11F3 => ReadBuffer,
+            0x11F4 => WriteBuffer,
+            0x11F5 => CopyBuffer,
+            0x11F6 => ReadImage,
+            0x11F7 => WriteImage,
+            0x11F8 => CopyImage,
+            0x11F9 => CopyImageToBuffer,
+            0x11FA => CopyBufferToImage,
+            0x11FB => MapBuffer,
+            0x11FC => MapImage,
+            0x11FD => UnmapMemObject,
+            0x11FE => Marker,
+            0x11FF => AcquireGLObjects,
+            0x1200 => ReleaseGLObjects,
+            0x1201 => ReadBufferRect,
+            0x1202 => WriteBufferRect,
+            0x1203 => CopyBufferRect,
+            0x1204 => User,
+            0x1205 => Barrier,
+            0x1206 => MigrateMemObjects,
+            0x1207 => FillBuffer,
+            0x1208 => FillImage,
+            _ => Other(code),
+        }
+    }
+}
+
+impl Event {
+    /// Wraps an already-retained event id
+    pub fn new(id: ll::Event) -> Event {
+        Event{
+            id: id,
+            waker: None,
+        }
+    }
+
+    fn get_info<T: Sized + Copy>(self: &Self, param: u32) -> Result<T, Error> {
+        let mut tr: T = unsafe { zeroed() };
         unsafe {
             try!(Error::check(ll::clGetEventInfo(self.id,
-                                                0x11D3,
-                                                size_of::<i32>() as size_t,
-                                                transmute(&mut code),
+                                                param,
+                                                size_of::<T>() as size_t,
+                                                transmute(&mut tr),
                                                 ptr::null_mut())));
         }
-        Ok(EventStatus::from_code(code))
+        Ok(tr)
+    }
+
+    fn execution_status(self: &Self) -> Result<i32, Error> {
+        self.get_info(0x11D3)
+    }
+
+    /// Waits for all of the given events with a single `clWaitForEvents` call
+    pub fn wait_all(events: &[&dyn EventLike]) -> Result<(), Error> {
+        if events.len() == 0 {
+            return Ok(());
+        }
+        let ids = wait_list_ids(events);
+        unsafe {
+            try!(Error::check(ll::clWaitForEvents(ids.len() as u32,
+                                                  wait_list_ptr(&ids))));
+        }
+        Ok(())
+    }
+
+    /// Blocks until any of the given events finishes and returns its index
+    ///
+    /// The event may have finished with an error; check its `status()`.
+    pub fn wait_any(events: &[&dyn EventLike]) -> Result<usize, Error> {
+        if events.len() == 0 {
+            return Err(Error::InvalidValue);
+        }
+        let (tx, rx) = mpsc::channel();
+        for (index, e) in events.iter().enumerate() {
+            let tx = tx.clone();
+            // later completions send to a closed channel, which is harmless
+            try!(e.event().on_complete(Box::new(move |_| {
+                let _ = tx.send(index);
+            })));
+        }
+        drop(tx);
+        Ok(rx.recv().ok().expect("event callbacks dropped without completing"))
+    }
+
+    /// Returns the kind of command this event belongs to
+    pub fn command_type(self: &Self) -> Result<CommandType, Error> {
+        let code: u32 = try!(self.get_info(0x11D1));
+        Ok(CommandType::from_code(code))
+    }
+
+    /// Returns the queue the command was enqueued on, or `None` for user events
+    ///
+    /// The returned handle doesn't finish on drop, whatever the original
+    /// handle's `set_finish_on_drop()` setting.
+    pub fn command_queue(self: &Self) -> Result<Option<CommandQueue>, Error> {
+        let id: ll::CommandQueue = try!(self.get_info(0x11D0));
+        if id.is_null() {
+            return Ok(None);
+        }
+        unsafe {
+            try!(Error::check(ll::clRetainCommandQueue(id)));
+        }
+        Ok(Some(CommandQueue::from_id(id)))
+    }
+
+    /// Returns the context the event belongs to
+    pub fn context(self: &Self) -> Result<Context, Error> {
+        let id: ll::Context = try!(self.get_info(0x11D4));
+        unsafe {
+            try!(Error::check(ll::clRetainContext(id)));
+        }
+        Ok(Context{id: id})
+    }
+
+    /// Returns the OpenCL reference count; only useful for debugging
+    pub fn reference_count(self: &Self) -> Result<u32, Error> {
+        self.get_info(0x11D2)
+    }
+
+    fn profiling_time(self: &Self, param: u32) -> Result<Duration, Error> {
+        let mut nanos: u64 = 0;
+        unsafe {
+            try!(Error::check(ll::clGetEventProfilingInfo(self.id,
+                                                          param,
+                                                          size_of::<u64>() as size_t,
+                                                          transmute(&mut nanos),
+                                                          ptr::null_mut())));
+        }
+        Ok(Duration::from_nanos(nanos))
+    }
+
+    /// Returns the command's timestamps
+    ///
+    /// Fails with `ProfilingInfoNotAvailable` if the command has not finished
+    /// or its queue was not created with profiling enabled.
+    pub fn profiling(self: &Self) -> Result<ProfilingInfo, Error> {
+        Ok(ProfilingInfo{
+            queued: try!(self.profiling_time(0x1280)),
+            submitted: try!(self.profiling_time(0x1281)),
+            start: try!(self.profiling_time(0x1282)),
+            end: try!(self.profiling_time(0x1283)),
+        })
+    }
+
+    /// Registers a callback for completion, successful or not
+    fn on_complete(self: &Self, callback: Callback) -> Result<(), Error> {
+        let user_data = Box::into_raw(Box::new(callback));
+        unsafe {
+            match Error::check(ll::clSetEventCallback(self.id,
+                                                      0, // CL_COMPLETE
+                                                      event_callback,
+                                                      user_data as *mut c_void)) {
+                Ok(()) => Ok(()),
+                Err(e) => {
+                    // OpenCL never saw the callback, so free it here
+                    drop(Box::from_raw(user_data));
+                    Err(e)
+                },
+            }
+        }
+    }
+}
+
+impl EventLike for Event {
+    fn event(self: &Self) -> &Event {
+        self
+    }
+
+    fn status(self: &Self) -> Result<EventStatus, Error> {
+        Ok(EventStatus::from_code(try!(self.execution_status())))
     }
 
     fn wait(self: &Self) -> Result<(), Error> {
@@ -61,6 +330,130 @@ impl EventLike for Event {
         }
         Ok(())
     }
+
+    fn then<F>(self: &Self, callback: F) -> Result<(), Error>
+        where F: FnOnce(EventStatus) + Send + 'static {
+        self.on_complete(Box::new(move |code| {
+            callback(EventStatus::from_code(code));
+        }))
+    }
+}
+
+impl ProfilingInfo {
+    /// Returns how long the command spent executing
+    ///
+    /// Zero if the driver reports an end before the start.
+    pub fn duration(self: &Self) -> Duration {
+        self.end.checked_sub(self.start).unwrap_or(Duration::from_secs(0))
+    }
+}
+
+impl UserEvent {
+    pub fn new(context: Context) -> Result<UserEvent, Error> {
+        let id = unsafe {
+            let mut err: i32 = 0;
+            let id = ll::clCreateUserEvent(context.id, &mut err);
+            try!(Error::check(err));
+            id
+        };
+        Ok(UserEvent{ evt: Event::new(id), status_set: false })
+    }
+
+    fn set_status(self: &mut Self, status: i32) -> Result<(), Error> {
+        unsafe {
+            try!(Error::check(ll::clSetUserEventStatus(self.evt.id, status)));
+        }
+        self.status_set = true;
+        Ok(())
+    }
+
+    /// Marks the event complete, releasing commands that wait on it
+    pub fn set_complete(self: &mut Self) -> Result<(), Error> {
+        self.set_status(0)
+    }
+
+    /// Marks the event failed with a negative error code, aborting commands
+    /// that wait on it
+    pub fn set_failed(self: &mut Self, code: i32) -> Result<(), Error> {
+        if code >= 0 {
+            return Err(Error::InvalidValue);
+        }
+        self.set_status(code)
+    }
+}
+
+impl EventLike for UserEvent {
+    fn event(self: &Self) -> &Event {
+        &self.evt
+    }
+
+    fn status(self: &Self) -> Result<EventStatus, Error> {
+        self.evt.status()
+    }
+
+    fn wait(self: &Self) -> Result<(), Error> {
+        self.evt.wait()
+    }
+
+    fn then<F>(self: &Self, callback: F) -> Result<(), Error>
+        where F: FnOnce(EventStatus) + Send + 'static {
+        self.evt.then(callback)
+    }
+}
+
+impl Drop for UserEvent {
+    fn drop(self: &mut Self) -> () {
+        if !self.status_set {
+            // CL_EXEC_STATUS_ERROR_FOR_EVENTS_IN_WAIT_LIST
+            self.set_status(-14).ok().expect("error failing abandoned user event");
+        }
+    }
+}
+
+/// Resolves when the command finishes
+///
+/// The first unfinished poll registers a completion callback; later polls
+/// only update the waker it calls.  No thread is blocked waiting on OpenCL.
+impl Future for Event {
+    type Output = Result<(), Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context) -> Poll<Result<(), Error>> {
+        let this = self.get_mut();
+        let code = match this.execution_status() {
+            Ok(code) => code,
+            Err(e) => return Poll::Ready(Err(e)),
+        };
+        if code <= 0 {
+            // complete, or negative if the command terminated abnormally
+            return Poll::Ready(Error::check(code));
+        }
+
+        match this.waker {
+            Some(ref slot) => {
+                *slot.lock().unwrap() = Some(cx.waker().clone());
+            },
+            None => {
+                let slot: WakerSlot = Arc::new(Mutex::new(Some(cx.waker().clone())));
+                let shared = slot.clone();
+                let registered = this.on_complete(Box::new(move |_| {
+                    if let Some(waker) = shared.lock().unwrap().take() {
+                        waker.wake();
+                    }
+                }));
+                if let Err(e) = registered {
+                    return Poll::Ready(Err(e));
+                }
+                this.waker = Some(slot);
+            },
+        }
+
+        // the callback may have fired before the new waker was stored
+        match this.execution_status() {
+            Ok(code) if code <= 0 => Poll::Ready(Error::check(code)),
+            Ok(_) => Poll::Pending,
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
 }
 
 impl Drop for Event {
@@ -78,7 +471,7 @@ impl Clone for Event {
             Error::check(ll::clRetainEvent(self.id))
                 .ok().expect("error retaining event");
         }
-        Event{ id: self.id }
+        Event::new(self.id)
     }
 }
 
@@ -0,0 +1,574 @@
+extern crate libc;
+use self::libc::*;
+
+use low_level as ll;
+use error::Error;
+use command_queue::CommandQueue;
+use device::Device;
+use context::Context;
+use mem::{Mem, MemLike, MemFlags, MemObjectType};
+
+use std::ptr;
+
+/// Order of the channels in an image element
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelOrder {
+    R,
+    A,
+    RG,
+    RA,
+    RGB,
+    RGBA,
+    BGRA,
+    ARGB,
+    Intensity,
+    Luminance,
+    Rx,
+    RGx,
+    RGBx,
+}
+
+/// Data type of each channel in an image element
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelType {
+    SNormInt8,
+    SNormInt16,
+    UNormInt8,
+    UNormInt16,
+    UNormShort565,
+    UNormShort555,
+    UNormInt101010,
+    SignedInt8,
+    SignedInt16,
+    SignedInt32,
+    UnsignedInt8,
+    UnsignedInt16,
+    UnsignedInt32,
+    HalfFloat,
+    Float,
+}
+
+/// OpenCL image format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageFormat {
+    pub order: ChannelOrder,
+    pub data_type: ChannelType,
+}
+
+/// Anything that wraps an OpenCL image
+///
+/// `dimensions()` is the full region of the image in the form OpenCL
+/// expects for transfers: (width, height, depth), with the array size
+/// taking the place of the first unused dimension for image arrays.
+pub trait ImageLike: MemLike {
+    fn format(self: &Self) -> ImageFormat;
+    fn dimensions(self: &Self) -> (usize, usize, usize);
+}
+
+/// One-dimensional OpenCL image
+pub struct Image1D {
+    mem: Mem,
+    format: ImageFormat,
+    width: usize,
+}
+
+/// Two-dimensional OpenCL image
+pub struct Image2D {
+    mem: Mem,
+    format: ImageFormat,
+    width: usize,
+    height: usize,
+}
+
+/// Three-dimensional OpenCL image
+pub struct Image3D {
+    mem: Mem,
+    format: ImageFormat,
+    width: usize,
+    height: usize,
+    depth: usize,
+}
+
+/// Array of one-dimensional OpenCL images
+pub struct Image1DArray {
+    mem: Mem,
+    format: ImageFormat,
+    width: usize,
+    array_size: usize,
+}
+
+/// Array of two-dimensional OpenCL images
+pub struct Image2DArray {
+    mem: Mem,
+    format: ImageFormat,
+    width: usize,
+    height: usize,
+    array_size: usize,
+}
+
+impl ChannelOrder {
+    pub fn from_code(code: u32) -> Option<ChannelOrder> {
+        use self::ChannelOrder::*;
+        match code {
+            0x10B0 => Some(R),
+            0x10B1 => Some(A),
+            0x10B2 => Some(RG),
+            0x10B3 => Some(RA),
+            0x10B4 => Some(RGB),
+            0x10B5 => Some(RGBA),
+            0x10B6 => Some(BGRA),
+            0x10B7 => Some(ARGB),
+            0x10B8 => Some(Intensity),
+            0x10B9 => Some(Luminance),
+            0x10BA => Some(Rx),
+            0x10BB => Some(RGx),
+            0x10BC => Some(RGBx),
+            _ => None,
+        }
+    }
+
+    pub fn code(self: &Self) -> u32 {
+        use self::ChannelOrder::*;
+        match *self {
+            R => 0x10B0,
+            A => 0x10B1,
+            RG => 0x10B2,
+            RA => 0x10B3,
+            RGB => 0x10B4,
+            RGBA => 0x10B5,
+            BGRA => 0x10B6,
+            ARGB => 0x10B7,
+            Intensity => 0x10B8,
+            Luminance => 0x10B9,
+            Rx => 0x10BA,
+            RGx => 0x10BB,
+            RGBx => 0x10BC,
+        }
+    }
+
+    /// Returns the number of channels in an element
+    pub fn channels(self: &Self) -> usize {
+        use self::ChannelOrder::*;
+        match *self {
+            R | A | Intensity | Luminance | Rx => 1,
+            RG | RA | RGx => 2,
+            RGB | RGBx => 3,
+            RGBA | BGRA | ARGB => 4,
+        }
+    }
+}
+
+impl ChannelType {
+    pub fn from_code(code: u32) -> Option<ChannelType> {
+        use self::ChannelType::*;
+        match code {
+            0x10D0 => Some(SNormInt8),
+            0x10D1 => Some(SNormInt16),
+            0x10D2 => Some(UNormInt8),
+            0x10D3 => Some(UNormInt16),
+            0x10D4 => Some(UNormShort565),
+            0x10D5 => Some(UNormShort555),
+            0x10D6 => Some(UNormInt101010),
+            0x10D7 => Some(SignedInt8),
+            0x10D8 => Some(SignedInt16),
+            0x10D9 => Some(SignedInt32),
+            0x10DA => Some(UnsignedInt8),
+            0x10DB => Some(UnsignedInt16),
+            0x10DC => Some(UnsignedInt32),
+            0x10DD => Some(HalfFloat),
+            0x10DE => Some(Float),
+            _ => None,
+        }
+    }
+
+    pub fn code(self: &Self) -> u32 {
+        use self::ChannelType::*;
+        match *self {
+            SNormInt8 => 0x10D0,
+            SNormInt16 => 0x10D1,
+            UNormInt8 => 0x10D2,
+            UNormInt16 => 0x10D3,
+            UNormShort565 => 0x10D4,
+            UNormShort555 => 0x10D5,
+            UNormInt101010 => 0x10D6,
+            SignedInt8 => 0x10D7,
+            SignedInt16 => 0x10D8,
+            SignedInt32 => 0x10D9,
+            UnsignedInt8 => 0x10DA,
+            UnsignedInt16 => 0x10DB,
+            UnsignedInt32 => 0x10DC,
+            HalfFloat => 0x10DD,
+            Float => 0x10DE,
+        }
+    }
+}
+
+impl ImageFormat {
+    pub fn new(order: ChannelOrder, data_type: ChannelType) -> ImageFormat {
+        ImageFormat{
+            order: order,
+            data_type: data_type,
+        }
+    }
+
+    /// Returns `None` if either code is not one proust knows about
+    pub fn from_ll(format: &ll::ImageFormat) -> Option<ImageFormat> {
+        match (ChannelOrder::from_code(format.image_channel_order),
+               ChannelType::from_code(format.image_channel_data_type)) {
+            (Some(order), Some(data_type)) => Some(ImageFormat::new(order, data_type)),
+            _ => None,
+        }
+    }
+
+    pub fn to_ll(self: &Self) -> ll::ImageFormat {
+        ll::ImageFormat{
+            image_channel_order: self.order.code(),
+            image_channel_data_type: self.data_type.code(),
+        }
+    }
+
+    /// Returns the size of one image element in bytes
+    pub fn element_size(self: &Self) -> usize {
+        use self::ChannelType::*;
+        match self.data_type {
+            // packed formats store a whole element in one value
+            UNormShort565 | UNormShort555 => 2,
+            UNormInt101010 => 4,
+            SNormInt8 | UNormInt8 | SignedInt8 | UnsignedInt8 => self.order.channels(),
+            SNormInt16 | UNormInt16 | SignedInt16 | UnsignedInt16 | HalfFloat => 2*self.order.channels(),
+            SignedInt32 | UnsignedInt32 | Float => 4*self.order.channels(),
+        }
+    }
+}
+
+fn check_image_support(device: &Device) -> Result<(), Error> {
+    if !try!(device.image_support()) {
+        return Err(Error::InvalidOperation);
+    }
+    Ok(())
+}
+
+fn check_image_size(size: usize, max: usize) -> Result<(), Error> {
+    if size == 0 || size > max {
+        return Err(Error::InvalidImageSize);
+    }
+    Ok(())
+}
+
+fn check_format_supported(context: &Context,
+                          flags: MemFlags,
+                          format: &ImageFormat,
+                          image_type: MemObjectType) -> Result<(), Error> {
+    let formats = try!(context.supported_image_formats(flags, image_type));
+    if !formats.contains(format) {
+        return Err(Error::ImageFormatNotSupported(
+                format!("{:?} channels of {:?} for {:?} with {:?}",
+                        format.order, format.data_type, image_type, flags)));
+    }
+    Ok(())
+}
+
+/// Creates an image through `clCreateImage` (OpenCL 1.2)
+fn create_image(queue: &CommandQueue,
+                flags: MemFlags,
+                format: &ImageFormat,
+                image_type: MemObjectType,
+                dimensions: (usize, usize, usize),
+                array_size: usize) -> Result<Mem, Error> {
+    let context = try!(queue.context());
+    try!(check_format_supported(&context, flags, format, image_type));
+    let ll_format = format.to_ll();
+    let desc = ll::ImageDesc{
+        image_type: image_type.code(),
+        image_width: dimensions.0 as size_t,
+        image_height: dimensions.1 as size_t,
+        image_depth: dimensions.2 as size_t,
+        image_array_size: array_size as size_t,
+        image_row_pitch: 0,
+        image_slice_pitch: 0,
+        num_mip_levels: 0,
+        num_samples: 0,
+        buffer: ptr::null_mut(),
+    };
+    let id = unsafe {
+        let mut err: i32 = 0;
+        let id = ll::clCreateImage(context.id,
+                                   flags.bits as c_ulong,
+                                   &ll_format,
+                                   &desc,
+                                   ptr::null_mut(),
+                                   &mut err);
+        try!(Error::check(err));
+        id
+    };
+    Ok(Mem::new(id))
+}
+
+impl Image1D {
+    /// Create a 1D image (OpenCL 1.2)
+    pub fn new(queue: &CommandQueue,
+               flags: MemFlags,
+               format: ImageFormat,
+               width: usize) -> Result<Image1D, Error> {
+        let device = try!(queue.device());
+        try!(check_image_support(&device));
+        try!(check_image_size(width, try!(device.image2d_max_width())));
+        let mem = try!(create_image(queue, flags, &format,
+                                    MemObjectType::Image1D,
+                                    (width, 0, 0), 0));
+        Ok(Image1D{ mem: mem, format: format, width: width })
+    }
+}
+
+impl Image2D {
+    pub fn new(queue: &CommandQueue,
+               flags: MemFlags,
+               format: ImageFormat,
+               width: usize,
+               height: usize) -> Result<Image2D, Error> {
+        let device = try!(queue.device());
+        try!(check_image_support(&device));
+        try!(check_image_size(width, try!(device.image2d_max_width())));
+        try!(check_image_size(height, try!(device.image2d_max_height())));
+        let context = try!(queue.context());
+        try!(check_format_supported(&context, flags, &format, MemObjectType::Image2D));
+        let ll_format = format.to_ll();
+        let id = unsafe {
+            let mut err: i32 = 0;
+            let id = ll::clCreateImage2D(context.id,
+                                         flags.bits as c_ulong,
+                                         &ll_format,
+                                         width as size_t,
+                                         height as size_t,
+                                         0,
+                                         ptr::null_mut(),
+                                         &mut err);
+            try!(Error::check(err));
+            id
+        };
+        Ok(Image2D{
+            mem: Mem::new(id),
+            format: format,
+            width: width,
+            height: height,
+        })
+    }
+}
+
+impl Image3D {
+    pub fn new(queue: &CommandQueue,
+               flags: MemFlags,
+               format: ImageFormat,
+               width: usize,
+               height: usize,
+               depth: usize) -> Result<Image3D, Error> {
+        let device = try!(queue.device());
+        try!(check_image_support(&device));
+        try!(check_image_size(width, try!(device.image3d_max_width())));
+        try!(check_image_size(height, try!(device.image3d_max_height())));
+        try!(check_image_size(depth, try!(device.image3d_max_depth())));
+        let context = try!(queue.context());
+        try!(check_format_supported(&context, flags, &format, MemObjectType::Image3D));
+        let ll_format = format.to_ll();
+        let id = unsafe {
+            let mut err: i32 = 0;
+            let id = ll::clCreateImage3D(context.id,
+                                         flags.bits as c_ulong,
+                                         &ll_format,
+                                         width as size_t,
+                                         height as size_t,
+                                         depth as size_t,
+                                         0,
+                                         0,
+                                         ptr::null_mut(),
+                                         &mut err);
+            try!(Error::check(err));
+            id
+        };
+        Ok(Image3D{
+            mem: Mem::new(id),
+            format: format,
+            width: width,
+            height: height,
+            depth: depth,
+        })
+    }
+}
+
+impl Image1DArray {
+    /// Create an array of 1D images (OpenCL 1.2)
+    pub fn new(queue: &CommandQueue,
+               flags: MemFlags,
+               format: ImageFormat,
+               width: usize,
+               array_size: usize) -> Result<Image1DArray, Error> {
+        let device = try!(queue.device());
+        try!(check_image_support(&device));
+        try!(check_image_size(width, try!(device.image2d_max_width())));
+        try!(check_image_size(array_size, try!(device.image_max_array_size())));
+        let mem = try!(create_image(queue, flags, &format,
+                                    MemObjectType::Image1DArray,
+                                    (width, 0, 0), array_size));
+        Ok(Image1DArray{
+            mem: mem,
+            format: format,
+            width: width,
+            array_size: array_size,
+        })
+    }
+}
+
+impl Image2DArray {
+    /// Create an array of 2D images (OpenCL 1.2)
+    pub fn new(queue: &CommandQueue,
+               flags: MemFlags,
+               format: ImageFormat,
+               width: usize,
+               height: usize,
+               array_size: usize) -> Result<Image2DArray, Error> {
+        let device = try!(queue.device());
+        try!(check_image_support(&device));
+        try!(check_image_size(width, try!(device.image2d_max_width())));
+        try!(check_image_size(height, try!(device.image2d_max_height())));
+        try!(check_image_size(array_size, try!(device.image_max_array_size())));
+        let mem = try!(create_image(queue, flags, &format,
+                                    MemObjectType::Image2DArray,
+                                    (width, height, 0), array_size));
+        Ok(Image2DArray{
+            mem: mem,
+            format: format,
+            width: width,
+            height: height,
+            array_size: array_size,
+        })
+    }
+}
+
+impl MemLike for Image1D {
+    fn mem(self: &Self) -> &Mem {
+        &self.mem
+    }
+}
+
+impl ImageLike for Image1D {
+    fn format(self: &Self) -> ImageFormat {
+        self.format
+    }
+
+    fn dimensions(self: &Self) -> (usize, usize, usize) {
+        (self.width, 1, 1)
+    }
+}
+
+impl MemLike for Image2D {
+    fn mem(self: &Self) -> &Mem {
+        &self.mem
+    }
+}
+
+impl ImageLike for Image2D {
+    fn format(self: &Self) -> ImageFormat {
+        self.format
+    }
+
+    fn dimensions(self: &Self) -> (usize, usize, usize) {
+        (self.width, self.height, 1)
+    }
+}
+
+impl MemLike for Image3D {
+    fn mem(self: &Self) -> &Mem {
+        &self.mem
+    }
+}
+
+impl ImageLike for Image3D {
+    fn format(self: &Self) -> ImageFormat {
+        self.format
+    }
+
+    fn dimensions(self: &Self) -> (usize, usize, usize) {
+        (self.width, self.height, self.depth)
+    }
+}
+
+impl MemLike for Image1DArray {
+    fn mem(self: &Self) -> &Mem {
+        &self.mem
+    }
+}
+
+impl ImageLike for Image1DArray {
+    fn format(self: &Self) -> ImageFormat {
+        self.format
+    }
+
+    fn dimensions(self: &Self) -> (usize, usize, usize) {
+        (self.width, self.array_size, 1)
+    }
+}
+
+impl MemLike for Image2DArray {
+    fn mem(self: &Self) -> &Mem {
+        &self.mem
+    }
+}
+
+impl ImageLike for Image2DArray {
+    fn format(self: &Self) -> ImageFormat {
+        self.format
+    }
+
+    fn dimensions(self: &Self) -> (usize, usize, usize) {
+        (self.width, self.height, self.array_size)
+    }
+}
+
+impl Clone for Image1D {
+    fn clone(self: &Self) -> Self {
+        Image1D{ mem: self.mem.clone(), .. *self }
+    }
+}
+
+impl Clone for Image2D {
+    fn clone(self: &Self) -> Self {
+        Image2D{ mem: self.mem.clone(), .. *self }
+    }
+}
+
+impl Clone for Image3D {
+    fn clone(self: &Self) -> Self {
+        Image3D{ mem: self.mem.clone(), .. *self }
+    }
+}
+
+impl Clone for Image1DArray {
+    fn clone(self: &Self) -> Self {
+        Image1DArray{ mem: self.mem.clone(), .. *self }
+    }
+}
+
+impl Clone for Image2DArray {
+    fn clone(self: &Self) -> Self {
+        Image2DArray{ mem: self.mem.clone(), .. *self }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ImageFormat, ChannelOrder, ChannelType};
+
+    #[test]
+    fn element_size_scales_with_channels() {
+        assert_eq!(ImageFormat::new(ChannelOrder::R, ChannelType::UNormInt8).element_size(), 1);
+        assert_eq!(ImageFormat::new(ChannelOrder::RG, ChannelType::HalfFloat).element_size(), 4);
+        assert_eq!(ImageFormat::new(ChannelOrder::RGBA, ChannelType::Float).element_size(), 16);
+        assert_eq!(ImageFormat::new(ChannelOrder::BGRA, ChannelType::UnsignedInt16).element_size(), 8);
+    }
+
+    #[test]
+    fn packed_formats_have_fixed_size() {
+        assert_eq!(ImageFormat::new(ChannelOrder::RGB, ChannelType::UNormShort565).element_size(), 2);
+        assert_eq!(ImageFormat::new(ChannelOrder::RGBx, ChannelType::UNormInt101010).element_size(), 4);
+    }
+}
@@ -3,13 +3,174 @@ use self::libc::*;
 
 use low_level as ll;
 use error::Error;
-use mem::Mem;
+use context::Context;
+use device::Device;
+use program::BuiltProgram;
+use mem::MemLike;
+use sampler::Sampler;
 
 use std::ptr;
-use std::mem::{transmute, size_of};
+use std::mem::{transmute, size_of, zeroed};
+use std::iter::repeat;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 
 pub struct Kernel {
     pub id: ll::Kernel,
+    arg_info: RefCell<ArgInfoCache>,
+    // bytes bound by `bind_local()`, per argument; shared with clones,
+    // which share the kernel's arguments
+    local_args: Arc<Mutex<HashMap<u32, usize>>>,
+}
+
+/// Reflection info for one kernel argument
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArgInfo {
+    pub name: String,
+    pub type_name: String,
+    pub address_qualifier: AddressQualifier,
+    pub access_qualifier: AccessQualifier,
+}
+
+/// Argument info, queried from OpenCL on first use
+#[derive(Clone)]
+enum ArgInfoCache {
+    Unknown,
+    Unavailable,
+    Loaded(Vec<ArgInfo>),
+}
+
+/// Rust types that can be passed to `Kernel::bind_scalar()`
+///
+/// `cl_type()` names the matching OpenCL C type, which is checked against
+/// the kernel argument when argument info is available.  Implement this
+/// for `#[repr(C)]` structs with `cl_type()` returning `None` to skip the
+/// check.
+pub trait KernelArgType: Sized {
+    fn cl_type() -> Option<&'static str>;
+}
+
+macro_rules! kernel_arg_types {
+    ($($t:ty => $name:tt),*) => {
+        $(
+            impl KernelArgType for $t {
+                fn cl_type() -> Option<&'static str> { Some($name) }
+            }
+            impl KernelArgType for [$t; 2] {
+                fn cl_type() -> Option<&'static str> { Some(concat!($name, "2")) }
+            }
+            impl KernelArgType for [$t; 4] {
+                fn cl_type() -> Option<&'static str> { Some(concat!($name, "4")) }
+            }
+            impl KernelArgType for [$t; 8] {
+                fn cl_type() -> Option<&'static str> { Some(concat!($name, "8")) }
+            }
+            impl KernelArgType for [$t; 16] {
+                fn cl_type() -> Option<&'static str> { Some(concat!($name, "16")) }
+            }
+        )*
+    }
+}
+
+kernel_arg_types!(i8 => "char", u8 => "uchar",
+                  i16 => "short", u16 => "ushort",
+                  i32 => "int", u32 => "uint",
+                  i64 => "long", u64 => "ulong",
+                  f32 => "float", f64 => "double");
+
+/// Address space of a kernel argument
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressQualifier {
+    Global,
+    Local,
+    Constant,
+    Private,
+    Other(u32),
+}
+
+/// Image access qualifier of a kernel argument
+///
+/// Arguments that are not images report `None`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessQualifier {
+    ReadOnly,
+    WriteOnly,
+    ReadWrite,
+    None,
+    Other(u32),
+}
+
+impl AddressQualifier {
+    pub fn from_code(code: u32) -> AddressQualifier {
+        match code {
+            0x119B => AddressQualifier::Global,
+            0x119C => AddressQualifier::Local,
+            0x119D => AddressQualifier::Constant,
+            0x119E => AddressQualifier::Private,
+            _ => AddressQualifier::Other(code),
+        }
+    }
+}
+
+impl AccessQualifier {
+    pub fn from_code(code: u32) -> AccessQualifier {
+        match code {
+            0x11A0 => AccessQualifier::ReadOnly,
+            0x11A1 => AccessQualifier::WriteOnly,
+            0x11A2 => AccessQualifier::ReadWrite,
+            0x11A3 => AccessQualifier::None,
+            _ => AccessQualifier::Other(code),
+        }
+    }
+}
+
+/// Spells unsigned types the way OpenCL C's short names do, e.g.,
+/// `unsigned int` as `uint`
+fn normalize_type_name(type_name: &str) -> String {
+    if type_name.starts_with("unsigned ") {
+        format!("u{}", &type_name["unsigned ".len()..])
+    } else {
+        String::from(type_name)
+    }
+}
+
+/// Size in bytes of an OpenCL C scalar or vector type, if known
+fn type_size(type_name: &str) -> Option<usize> {
+    let base = type_name.trim_end_matches(|c: char| c.is_digit(10));
+    let width = match &type_name[base.len()..] {
+        "" => 1,
+        "2" => 2,
+        "3" | "4" => 4, // 3-vectors are padded to 4
+        "8" => 8,
+        "16" => 16,
+        _ => return None,
+    };
+    let elem = match base {
+        "char" | "uchar" => 1,
+        "short" | "ushort" | "half" => 2,
+        "int" | "uint" | "float" => 4,
+        "long" | "ulong" | "double" => 8,
+        _ => return None,
+    };
+    Some(elem * width)
+}
+
+/// Whether a Rust type's OpenCL type name fits an argument's type
+///
+/// A 4-vector is accepted for a 3-vector argument, since 3-vectors are
+/// padded to 4 and there are no Rust 3-vector types.
+fn cl_type_matches(arg_type: &str, cl_type: &str) -> bool {
+    if arg_type == cl_type {
+        return true;
+    }
+    match (arg_type.len().checked_sub(1), cl_type.len().checked_sub(1)) {
+        (Some(a), Some(c)) => {
+            &arg_type[a..] == "3" && &cl_type[c..] == "4"
+                && arg_type[..a] == cl_type[..c]
+        },
+        _ => false,
+    }
 }
 
 impl Drop for Kernel {
@@ -29,6 +190,8 @@ impl Clone for Kernel {
         };
         Kernel{ 
             id: self.id, 
+            arg_info: self.arg_info.clone(),
+            local_args: self.local_args.clone(),
         }
     }
 }
@@ -39,9 +202,290 @@ impl Kernel {
     pub fn new(id: ll::Kernel) -> Kernel {
         Kernel{
             id: id,
+            arg_info: RefCell::new(ArgInfoCache::Unknown),
+            local_args: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
+    fn get_info<T: Sized + Copy>(self: &Self, param: u32) -> Result<T, Error> {
+        let mut tr: T = unsafe { zeroed() };
+        unsafe {
+            try!(Error::check(ll::clGetKernelInfo(self.id,
+                                                  param,
+                                                  size_of::<T>() as size_t,
+                                                  transmute(&mut tr),
+                                                  ptr::null_mut())));
+        }
+        Ok(tr)
+    }
+
+    fn get_work_group_info<T: Sized + Copy>(self: &Self,
+                                            device: &Device,
+                                            param: u32) -> Result<T, Error> {
+        let mut tr: T = unsafe { zeroed() };
+        unsafe {
+            try!(Error::check(ll::clGetKernelWorkGroupInfo(self.id,
+                                                           device.id,
+                                                           param,
+                                                           size_of::<T>() as size_t,
+                                                           transmute(&mut tr),
+                                                           ptr::null_mut())));
+        }
+        Ok(tr)
+    }
+
+    /// Returns the name of the kernel function
+    pub fn function_name(self: &Self) -> Result<String, Error> {
+        let size_req = unsafe {
+            let mut tr: size_t = 0;
+            try!(Error::check(ll::clGetKernelInfo(self.id,
+                                                  0x1190,
+                                                  0,
+                                                  ptr::null_mut(),
+                                                  &mut tr)));
+            tr as usize
+        };
+        let mut buf: Vec<u8> = repeat(0).take(size_req).collect();
+        unsafe {
+            try!(Error::check(ll::clGetKernelInfo(self.id,
+                                                  0x1190,
+                                                  size_req as size_t,
+                                                  transmute(&mut buf[0]),
+                                                  ptr::null_mut())));
+        }
+        // drop the trailing nul
+        buf.pop();
+        Ok(String::from_utf8(buf).ok().expect("CL returned invalid string"))
+    }
+
+    /// Returns the number of arguments the kernel takes
+    pub fn num_args(self: &Self) -> Result<u32, Error> {
+        self.get_info(0x1191)
+    }
+
+    /// Returns the OpenCL reference count; only useful for debugging
+    pub fn reference_count(self: &Self) -> Result<u32, Error> {
+        self.get_info(0x1192)
+    }
+
+    /// Returns the context the kernel was created in
+    pub fn context(self: &Self) -> Result<Context, Error> {
+        let id: ll::Context = try!(self.get_info(0x1193));
+        unsafe {
+            try!(Error::check(ll::clRetainContext(id)));
+        }
+        Ok(Context{id: id})
+    }
+
+    /// Returns the program the kernel was created from
+    pub fn program(self: &Self) -> Result<BuiltProgram, Error> {
+        let id: ll::Program = try!(self.get_info(0x1194));
+        unsafe {
+            try!(Error::check(ll::clRetainProgram(id)));
+        }
+        Ok(BuiltProgram{id: id})
+    }
+
+    /// Returns the largest work-group size this kernel can run with on `device`
+    pub fn work_group_size(self: &Self, device: &Device) -> Result<usize, Error> {
+        Ok(try!(self.get_work_group_info::<size_t>(device, 0x11B0)) as usize)
+    }
+
+    /// Returns the size given by `reqd_work_group_size`, or zeros if none
+    pub fn compile_work_group_size(self: &Self, device: &Device) -> Result<(usize, usize, usize), Error> {
+        let sizes: [size_t; 3] = try!(self.get_work_group_info(device, 0x11B1));
+        Ok((sizes[0] as usize, sizes[1] as usize, sizes[2] as usize))
+    }
+
+    /// Returns the local memory used by the kernel on `device`, in bytes
+    pub fn local_mem_size(self: &Self, device: &Device) -> Result<usize, Error> {
+        Ok(try!(self.get_work_group_info::<u64>(device, 0x11B2)) as usize)
+    }
+
+    /// Returns the work-group size multiple that performs best on `device`
+    pub fn preferred_work_group_size_multiple(self: &Self, device: &Device) -> Result<usize, Error> {
+        Ok(try!(self.get_work_group_info::<size_t>(device, 0x11B3)) as usize)
+    }
+
+    /// Returns the private memory used by each work-item on `device`, in bytes
+    pub fn private_mem_size(self: &Self, device: &Device) -> Result<usize, Error> {
+        Ok(try!(self.get_work_group_info::<u64>(device, 0x11B4)) as usize)
+    }
+
+    fn get_arg_info<T: Sized + Copy>(self: &Self, index: u32, param: u32) -> Result<T, Error> {
+        let mut tr: T = unsafe { zeroed() };
+        unsafe {
+            try!(Error::check(ll::clGetKernelArgInfo(self.id,
+                                                     index,
+                                                     param,
+                                                     size_of::<T>() as size_t,
+                                                     transmute(&mut tr),
+                                                     ptr::null_mut())));
+        }
+        Ok(tr)
+    }
+
+    fn get_arg_info_string(self: &Self, index: u32, param: u32) -> Result<String, Error> {
+        let size_req = unsafe {
+            let mut tr: size_t = 0;
+            try!(Error::check(ll::clGetKernelArgInfo(self.id,
+                                                     index,
+                                                     param,
+                                                     0,
+                                                     ptr::null_mut(),
+                                                     &mut tr)));
+            tr as usize
+        };
+        let mut buf: Vec<u8> = repeat(0).take(size_req).collect();
+        unsafe {
+            try!(Error::check(ll::clGetKernelArgInfo(self.id,
+                                                     index,
+                                                     param,
+                                                     size_req as size_t,
+                                                     transmute(&mut buf[0]),
+                                                     ptr::null_mut())));
+        }
+        // drop the trailing nul
+        buf.pop();
+        Ok(String::from_utf8(buf).ok().expect("CL returned invalid string"))
+    }
+
+    fn query_arg_info(self: &Self, index: u32) -> Result<ArgInfo, Error> {
+        let address: u32 = try!(self.get_arg_info(index, 0x1196));
+        let access: u32 = try!(self.get_arg_info(index, 0x1197));
+        Ok(ArgInfo{
+            name: try!(self.get_arg_info_string(index, 0x119A)),
+            type_name: normalize_type_name(&try!(self.get_arg_info_string(index, 0x1198))),
+            address_qualifier: AddressQualifier::from_code(address),
+            access_qualifier: AccessQualifier::from_code(access),
+        })
+    }
+
+    /// Fills the argument info cache; OpenCL is only queried once
+    fn load_arg_info(self: &Self) -> Result<(), Error> {
+        match *self.arg_info.borrow() {
+            ArgInfoCache::Unknown => {},
+            _ => return Ok(()),
+        }
+        let mut infos = Vec::new();
+        for index in 0..try!(self.num_args()) {
+            match self.query_arg_info(index) {
+                Ok(info) => infos.push(info),
+                Err(Error::KernelArgInfoNotAvailable) => {
+                    *self.arg_info.borrow_mut() = ArgInfoCache::Unavailable;
+                    return Ok(());
+                },
+                Err(e) => return Err(e),
+            }
+        }
+        *self.arg_info.borrow_mut() = ArgInfoCache::Loaded(infos);
+        Ok(())
+    }
+
+    /// Returns info for argument `index`, or `None` if the program was built
+    /// without `-cl-kernel-arg-info`
+    fn maybe_arg_info(self: &Self, index: u32) -> Result<Option<ArgInfo>, Error> {
+        try!(self.load_arg_info());
+        match *self.arg_info.borrow() {
+            ArgInfoCache::Loaded(ref infos) => match infos.get(index as usize) {
+                Some(info) => Ok(Some(info.clone())),
+                None => Err(Error::InvalidArgIndex),
+            },
+            _ => Ok(None),
+        }
+    }
+
+    /// Returns the name, type and qualifiers of argument `index`
+    ///
+    /// This, the other `arg_*` queries and the `*_by_name` methods need the
+    /// program built with `-cl-kernel-arg-info`; otherwise they fail with
+    /// `KernelArgInfoNotAvailable`.  Unsigned types are reported by their
+    /// short names, e.g., `uint`.
+    pub fn arg_info(self: &Self, index: u32) -> Result<ArgInfo, Error> {
+        match try!(self.maybe_arg_info(index)) {
+            Some(info) => Ok(info),
+            None => Err(Error::KernelArgInfoNotAvailable),
+        }
+    }
+
+    /// Returns the address space of argument `index`
+    pub fn arg_address_qualifier(self: &Self, index: u32) -> Result<AddressQualifier, Error> {
+        Ok(try!(self.arg_info(index)).address_qualifier)
+    }
+
+    /// Returns the image access qualifier of argument `index`
+    pub fn arg_access_qualifier(self: &Self, index: u32) -> Result<AccessQualifier, Error> {
+        Ok(try!(self.arg_info(index)).access_qualifier)
+    }
+
+    /// Returns the OpenCL C type of argument `index`, e.g., `float*`
+    pub fn arg_type_name(self: &Self, index: u32) -> Result<String, Error> {
+        Ok(try!(self.arg_info(index)).type_name)
+    }
+
+    /// Returns the name of argument `index` in the kernel source
+    pub fn arg_name(self: &Self, index: u32) -> Result<String, Error> {
+        Ok(try!(self.arg_info(index)).name)
+    }
+
+    /// Returns the index of the argument called `name`
+    pub fn arg_index(self: &Self, name: &str) -> Result<u32, Error> {
+        try!(self.load_arg_info());
+        match *self.arg_info.borrow() {
+            ArgInfoCache::Loaded(ref infos) => {
+                match infos.iter().position(|info| info.name == name) {
+                    Some(index) => Ok(index as u32),
+                    None => Err(Error::InvalidArgName(String::from(name))),
+                }
+            },
+            _ => Err(Error::KernelArgInfoNotAvailable),
+        }
+    }
+
+    fn check_scalar_arg<T: KernelArgType>(self: &Self, index: u32) -> Result<(), Error> {
+        let info = match try!(self.maybe_arg_info(index)) {
+            Some(info) => info,
+            None => return Ok(()),
+        };
+        if info.address_qualifier != AddressQualifier::Private {
+            return Err(Error::ArgTypeMismatch(
+                format!("argument {} is a {:?} {}, not a scalar",
+                        index, info.address_qualifier, info.type_name)));
+        }
+        // typedefs and structs aren't known, so only builtin types are checked
+        let size = match type_size(&info.type_name) {
+            Some(size) => size,
+            None => return Ok(()),
+        };
+        match T::cl_type() {
+            Some(cl_type) if !cl_type_matches(&info.type_name, cl_type) => Err(Error::ArgTypeMismatch(
+                format!("argument {} is {}, got {}", index, info.type_name, cl_type))),
+            _ if size != size_of::<T>() => Err(Error::ArgTypeMismatch(
+                format!("argument {} is {} ({} bytes), got {} bytes",
+                        index, info.type_name, size, size_of::<T>()))),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_arg_address(self: &Self,
+                         index: u32,
+                         allowed: &[AddressQualifier],
+                         what: &str) -> Result<(), Error> {
+        match try!(self.maybe_arg_info(index)) {
+            Some(ref info) if !allowed.contains(&info.address_qualifier) => {
+                Err(Error::ArgTypeMismatch(
+                    format!("argument {} is {:?}, not {}", index, info.address_qualifier, what)))
+            },
+            _ => Ok(()),
+        }
+    }
+
+    fn check_mem_arg(self: &Self, index: u32) -> Result<(), Error> {
+        self.check_arg_address(index,
+                               &[AddressQualifier::Global, AddressQualifier::Constant],
+                               "a global or constant pointer")
+    }
+
     pub fn bind_null(self: &mut Self, index: u32) -> Result<(), Error> {
         unsafe {
             try!(Error::check(ll::clSetKernelArg(self.id, index, size_of::<ll::Mem>() as size_t, ptr::null_mut())));
@@ -49,25 +493,145 @@ impl Kernel {
         Ok(())
     }
 
-    pub fn bind_scalar<T: Sized>(self: &mut Self, index: u32, val: &T) -> Result<(), Error> {
+    /// Binds a scalar or vector value
+    ///
+    /// If the program was built with `-cl-kernel-arg-info`, `T` is checked
+    /// against the argument's type.  Vectors bind as arrays, e.g., `[f32; 4]`
+    /// for `float4`.
+    pub fn bind_scalar<T: KernelArgType>(self: &mut Self, index: u32, val: &T) -> Result<(), Error> {
+        try!(self.check_scalar_arg::<T>(index));
         unsafe {
             try!(Error::check(ll::clSetKernelArg(self.id, index, size_of::<T>() as size_t, transmute(val))));
         }
         Ok(())
     }
 
-    pub fn bind(self: &mut Self, index: u32, buf: &Mem) -> Result<(), Error> {
+    pub fn bind<M: MemLike>(self: &mut Self, index: u32, buf: &M) -> Result<(), Error> {
+        try!(self.check_mem_arg(index));
+        unsafe {
+            try!(Error::check(ll::clSetKernelArg(self.id, index, size_of::<ll::Mem>() as size_t, transmute(&buf.mem().id))));
+        }
+        Ok(())
+    }
+
+    pub fn bind_mut<M: MemLike>(self: &mut Self, index: u32, buf: &mut M) -> Result<(), Error> {
+        try!(self.check_mem_arg(index));
         unsafe {
-            try!(Error::check(ll::clSetKernelArg(self.id, index, size_of::<ll::Mem>() as size_t, transmute(&buf.id))));
+            try!(Error::check(ll::clSetKernelArg(self.id, index, size_of::<ll::Mem>() as size_t, transmute(&buf.mem().id))));
         }
         Ok(())
     }
 
-    pub fn bind_mut(self: &mut Self, index: u32, buf: &mut Mem) -> Result<(), Error> {
+    pub fn bind_sampler(self: &mut Self, index: u32, sampler: &Sampler) -> Result<(), Error> {
         unsafe {
-            try!(Error::check(ll::clSetKernelArg(self.id, index, size_of::<ll::Mem>() as size_t, transmute(&buf.id))));
+            try!(Error::check(ll::clSetKernelArg(self.id, index, size_of::<ll::Sampler>() as size_t, transmute(&sampler.id))));
         }
         Ok(())
     }
+
+    /// Allocates `count` elements of `T` in local memory for a `__local` argument
+    ///
+    /// The kernel's total local memory use with the new size, including
+    /// static `__local` variables and other local arguments, is checked
+    /// against `device.local_mem_size()` first.  If it doesn't fit, the
+    /// argument is left as it was.
+    pub fn bind_local<T: Sized>(self: &mut Self,
+                                index: u32,
+                                count: usize,
+                                device: &Device) -> Result<(), Error> {
+        try!(self.check_arg_address(index, &[AddressQualifier::Local], "local"));
+        let size = match count.checked_mul(size_of::<T>()) {
+            Some(size) => size,
+            None => return Err(Error::InvalidArgSize),
+        };
+        let mut local_args = self.local_args.lock().unwrap();
+        // the kernel's usage counts the argument at its current size
+        let previous = local_args.get(&index).cloned().unwrap_or(0);
+        let others = try!(self.local_mem_size(device)).saturating_sub(previous);
+        let available = try!(device.local_mem_size());
+        match others.checked_add(size) {
+            Some(used) if used <= available => {},
+            Some(used) => return Err(Error::LocalMemExceeded(used, available)),
+            None => return Err(Error::LocalMemExceeded(usize::max_value(), available)),
+        }
+        unsafe {
+            try!(Error::check(ll::clSetKernelArg(self.id, index, size as size_t, ptr::null())));
+        }
+        local_args.insert(index, size);
+        Ok(())
+    }
+
+    /// Binds a scalar to the argument called `name`
+    ///
+    /// The `*_by_name` methods need the program built with
+    /// `-cl-kernel-arg-info`.
+    pub fn bind_scalar_by_name<T: KernelArgType>(self: &mut Self, name: &str, val: &T) -> Result<(), Error> {
+        let index = try!(self.arg_index(name));
+        self.bind_scalar(index, val)
+    }
+
+    pub fn bind_by_name<M: MemLike>(self: &mut Self, name: &str, buf: &M) -> Result<(), Error> {
+        let index = try!(self.arg_index(name));
+        self.bind(index, buf)
+    }
+
+    pub fn bind_mut_by_name<M: MemLike>(self: &mut Self, name: &str, buf: &mut M) -> Result<(), Error> {
+        let index = try!(self.arg_index(name));
+        self.bind_mut(index, buf)
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::{type_size, normalize_type_name, cl_type_matches, KernelArgType};
+
+    #[test]
+    fn scalar_and_vector_sizes() {
+        assert_eq!(type_size("char"), Some(1));
+        assert_eq!(type_size("half"), Some(2));
+        assert_eq!(type_size("float"), Some(4));
+        assert_eq!(type_size("ulong"), Some(8));
+        assert_eq!(type_size("float4"), Some(16));
+        assert_eq!(type_size("double16"), Some(128));
+    }
+
+    #[test]
+    fn three_vectors_are_padded() {
+        assert_eq!(type_size("float3"), Some(16));
+        assert_eq!(type_size("short3"), Some(8));
+    }
+
+    #[test]
+    fn unknown_types_have_no_size() {
+        assert_eq!(type_size("float*"), None);
+        assert_eq!(type_size("my_struct"), None);
+        assert_eq!(type_size("float5"), None);
+    }
+
+    #[test]
+    fn unsigned_names_are_normalized() {
+        assert_eq!(normalize_type_name("unsigned int"), "uint");
+        assert_eq!(normalize_type_name("unsigned char"), "uchar");
+        assert_eq!(normalize_type_name("int"), "int");
+    }
+
+    #[test]
+    fn rust_types_map_to_cl_types() {
+        assert_eq!(i32::cl_type(), Some("int"));
+        assert_eq!(u32::cl_type(), Some("uint"));
+        assert_eq!(f32::cl_type(), Some("float"));
+        assert_eq!(<[f32; 4]>::cl_type(), Some("float4"));
+        assert_eq!(<[u8; 16]>::cl_type(), Some("uchar16"));
+    }
+
+    #[test]
+    fn four_vectors_fit_three_vector_args() {
+        assert!(cl_type_matches("float", "float"));
+        assert!(cl_type_matches("float3", "float4"));
+        assert!(cl_type_matches("uchar3", <[u8; 4]>::cl_type().unwrap()));
+        assert!(!cl_type_matches("float3", "int4"));
+        assert!(!cl_type_matches("float4", "float3"));
+        assert!(!cl_type_matches("float3", "float2"));
+        assert!(!cl_type_matches("int", "uint"));
+    }
+}
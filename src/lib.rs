@@ -8,16 +8,25 @@ mod program;
 mod event;
 mod kernel;
 mod mem;
+mod buffer;
+mod image;
+mod sampler;
+mod nd_range;
 
 pub use self::error::Error;
 pub use self::platform::Platform;
 pub use self::device::{Device, DeviceType};
 pub use self::context::Context;
-pub use self::command_queue::CommandQueue;
-pub use self::event::{Event, EventStatus, EventLike};
+pub use self::command_queue::{CommandQueue, CommandQueueBuilder, ReadLock, WriteLock, MapMode, MapWritable, MapRead, MapWrite, MapWriteInvalidate, MappedBuffer, PendingMap};
+pub use self::event::{Event, EventStatus, EventLike, UserEvent, ProfilingInfo, CommandType};
 pub use self::program::{BuiltProgram, Program};
-pub use self::kernel::Kernel;
-pub use self::mem::Mem;
+pub use self::kernel::{Kernel, KernelArgType, ArgInfo, AddressQualifier, AccessQualifier};
+pub use self::nd_range::NDRange;
+pub use self::mem::{Mem, MemLike, MemFlags, MemObjectType, BufferBuilder, HostMem, HostMemArg};
+pub use self::buffer::Buffer;
+pub use self::image::{ChannelOrder, ChannelType, ImageFormat, ImageLike,
+                      Image1D, Image2D, Image3D, Image1DArray, Image2DArray};
+pub use self::sampler::{Sampler, AddressingMode, FilterMode};
 
 mod c_api;
 pub use c_api::*;
@@ -14,6 +14,33 @@ pub type Sampler = *mut c_void;
 pub type PlatformInfo = u32;
 pub type DeviceInfo = u32;
 
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct ImageFormat {
+    pub image_channel_order: u32,
+    pub image_channel_data_type: u32,
+}
+
+#[repr(C)]
+pub struct ImageDesc {
+    pub image_type: u32,
+    pub image_width: size_t,
+    pub image_height: size_t,
+    pub image_depth: size_t,
+    pub image_array_size: size_t,
+    pub image_row_pitch: size_t,
+    pub image_slice_pitch: size_t,
+    pub num_mip_levels: u32,
+    pub num_samples: u32,
+    pub buffer: Mem,
+}
+
+#[repr(C)]
+pub struct BufferRegion {
+    pub origin: size_t,
+    pub size: size_t,
+}
+
 #[cfg(target_os = "macos")]
 #[link(name = "OpenCL", kind = "framework")]
 extern { }
@@ -26,6 +53,16 @@ extern { }
 #[link(name = "OpenCL")]
 extern { }
 
+// OpenCL 2.0 entry points; macOS's framework and older ICD loaders lack
+// these, so linking against them is opt-in
+#[cfg(feature = "opencl2")]
+extern {
+    pub fn clCreateCommandQueueWithProperties(context: Context,
+                                              device: DeviceID,
+                                              properties: *const u64,
+                                              err: *mut i32) -> CommandQueue;
+}
+
 extern {
     pub fn clGetPlatformIDs(num_entries: u32,
                             platforms: *mut PlatformID,
@@ -80,6 +117,10 @@ extern {
 
     pub fn clReleaseCommandQueue(queue: CommandQueue) -> i32;
 
+    pub fn clFlush(queue: CommandQueue) -> i32;
+
+    pub fn clFinish(queue: CommandQueue) -> i32;
+
     pub fn clCreateProgramWithSource(context: Context,
                                      num_lines: u32,
                                      lines: *const *const u8,
@@ -117,6 +158,36 @@ extern {
 
     pub fn clReleaseKernel(kernel: Kernel) -> i32;
 
+    pub fn clGetKernelInfo(kernel: Kernel,
+                           param_name: u32,
+                           param_value_size: size_t,
+                           param_value: *mut c_void,
+                           param_value_size_ret: *mut size_t) -> i32;
+
+    pub fn clGetKernelArgInfo(kernel: Kernel,
+                              arg_index: u32,
+                              param_name: u32,
+                              param_value_size: size_t,
+                              param_value: *mut c_void,
+                              param_value_size_ret: *mut size_t) -> i32;
+
+    pub fn clGetKernelWorkGroupInfo(kernel: Kernel,
+                                    device: DeviceID,
+                                    param_name: u32,
+                                    param_value_size: size_t,
+                                    param_value: *mut c_void,
+                                    param_value_size_ret: *mut size_t) -> i32;
+
+    pub fn clCreateSampler(context: Context,
+                           normalized_coords: u32,
+                           addressing_mode: u32,
+                           filter_mode: u32,
+                           err: *mut i32) -> Sampler;
+
+    pub fn clRetainSampler(sampler: Sampler) -> i32;
+
+    pub fn clReleaseSampler(sampler: Sampler) -> i32;
+
     pub fn clRetainEvent(event: Event) -> i32;
 
     pub fn clReleaseEvent(event: Event) -> i32;
@@ -127,6 +198,23 @@ extern {
                           param_val: *mut c_void,
                           size_ret: *mut size_t) -> i32;
 
+    pub fn clCreateUserEvent(context: Context,
+                             err: *mut i32) -> Event;
+
+    pub fn clSetUserEventStatus(event: Event,
+                                status: i32) -> i32;
+
+    pub fn clSetEventCallback(event: Event,
+                              callback_type: i32,
+                              callback: extern fn (Event, i32, *mut c_void) -> (),
+                              user_data: *mut c_void) -> i32;
+
+    pub fn clGetEventProfilingInfo(event: Event,
+                                   param: u32,
+                                   param_size: size_t,
+                                   param_val: *mut c_void,
+                                   size_ret: *mut size_t) -> i32;
+
     pub fn clWaitForEvents(num_events: u32,
                            events: *const Event) -> i32;
 
@@ -134,12 +222,62 @@ extern {
 
     pub fn clReleaseMemObject(mem: Mem) -> i32;
 
+    pub fn clSetMemObjectDestructorCallback(mem: Mem,
+                                            callback: extern fn (Mem, *mut c_void) -> (),
+                                            user_data: *mut c_void) -> i32;
+
+    pub fn clGetMemObjectInfo(mem: Mem,
+                              param_name: u32,
+                              param_size: size_t,
+                              param_value: *mut c_void,
+                              size_ret: *mut size_t) -> i32;
+
     pub fn clCreateBuffer(ctx: Context,
                           flags: c_ulong,
                           size: size_t,
                           host_ptr: *const c_void,
                           error: *mut i32) -> Mem;
 
+    pub fn clCreateImage2D(ctx: Context,
+                           flags: c_ulong,
+                           format: *const ImageFormat,
+                           width: size_t,
+                           height: size_t,
+                           row_pitch: size_t,
+                           host_ptr: *mut c_void,
+                           error: *mut i32) -> Mem;
+
+    pub fn clCreateImage3D(ctx: Context,
+                           flags: c_ulong,
+                           format: *const ImageFormat,
+                           width: size_t,
+                           height: size_t,
+                           depth: size_t,
+                           row_pitch: size_t,
+                           slice_pitch: size_t,
+                           host_ptr: *mut c_void,
+                           error: *mut i32) -> Mem;
+
+    pub fn clCreateImage(ctx: Context,
+                         flags: c_ulong,
+                         format: *const ImageFormat,
+                         desc: *const ImageDesc,
+                         host_ptr: *mut c_void,
+                         error: *mut i32) -> Mem;
+
+    pub fn clGetSupportedImageFormats(ctx: Context,
+                                      flags: c_ulong,
+                                      image_type: u32,
+                                      num_entries: u32,
+                                      formats: *mut ImageFormat,
+                                      num_formats: *mut u32) -> i32;
+
+    pub fn clCreateSubBuffer(mem: Mem,
+                             flags: c_ulong,
+                             create_type: u32,
+                             create_info: *const c_void,
+                             error: *mut i32) -> Mem;
+
     pub fn clEnqueueWriteBuffer(queue: CommandQueue,
                                 mem: Mem,
                                 blocking: u32,
@@ -160,6 +298,88 @@ extern {
                                wait_list: *const Event,
                                event: *mut Event) -> i32;
 
+    pub fn clEnqueueReadBufferRect(queue: CommandQueue,
+                                   mem: Mem,
+                                   blocking: u32,
+                                   buffer_origin: *const size_t,
+                                   host_origin: *const size_t,
+                                   region: *const size_t,
+                                   buffer_row_pitch: size_t,
+                                   buffer_slice_pitch: size_t,
+                                   host_row_pitch: size_t,
+                                   host_slice_pitch: size_t,
+                                   host: *mut c_void,
+                                   num_events: u32,
+                                   events: *const Event,
+                                   event: *mut Event) -> i32;
+
+    pub fn clEnqueueWriteBufferRect(queue: CommandQueue,
+                                    mem: Mem,
+                                    blocking: u32,
+                                    buffer_origin: *const size_t,
+                                    host_origin: *const size_t,
+                                    region: *const size_t,
+                                    buffer_row_pitch: size_t,
+                                    buffer_slice_pitch: size_t,
+                                    host_row_pitch: size_t,
+                                    host_slice_pitch: size_t,
+                                    host: *const c_void,
+                                    num_events: u32,
+                                    events: *const Event,
+                                    event: *mut Event) -> i32;
+
+    pub fn clEnqueueCopyBufferRect(queue: CommandQueue,
+                                   src: Mem,
+                                   dst: Mem,
+                                   src_origin: *const size_t,
+                                   dst_origin: *const size_t,
+                                   region: *const size_t,
+                                   src_row_pitch: size_t,
+                                   src_slice_pitch: size_t,
+                                   dst_row_pitch: size_t,
+                                   dst_slice_pitch: size_t,
+                                   num_events: u32,
+                                   events: *const Event,
+                                   event: *mut Event) -> i32;
+
+    pub fn clEnqueueCopyBuffer(queue: CommandQueue,
+                               src: Mem,
+                               dst: Mem,
+                               src_offset: size_t,
+                               dst_offset: size_t,
+                               size: size_t,
+                               num_events: u32,
+                               events: *const Event,
+                               event: *mut Event) -> i32;
+
+    pub fn clEnqueueFillBuffer(queue: CommandQueue,
+                               mem: Mem,
+                               pattern: *const c_void,
+                               pattern_size: size_t,
+                               offset: size_t,
+                               size: size_t,
+                               num_events: u32,
+                               events: *const Event,
+                               event: *mut Event) -> i32;
+
+    pub fn clEnqueueMapBuffer(queue: CommandQueue,
+                              buffer: Mem,
+                              blocking: u32,
+                              map_flags: u64,
+                              offset: size_t,
+                              size: size_t,
+                              num_events: u32,
+                              events: *const Event,
+                              event: *mut Event,
+                              err: *mut i32) -> *mut c_void;
+
+    pub fn clEnqueueUnmapMemObject(queue: CommandQueue,
+                                   mem: Mem,
+                                   mapped_ptr: *mut c_void,
+                                   num_events: u32,
+                                   events: *const Event,
+                                   event: *mut Event) -> i32;
+
     pub fn clEnqueueNDRangeKernel(queue: CommandQueue,
                                   kernel: Kernel,
                                   dim: u32,
@@ -170,6 +390,68 @@ extern {
                                   events: *const Event,
                                   event: *mut Event) -> i32;
 
+    pub fn clEnqueueReadImage(queue: CommandQueue,
+                              image: Mem,
+                              blocking: u32,
+                              origin: *const size_t,
+                              region: *const size_t,
+                              row_pitch: size_t,
+                              slice_pitch: size_t,
+                              host: *mut c_void,
+                              num_events: u32,
+                              events: *const Event,
+                              event: *mut Event) -> i32;
+
+    pub fn clEnqueueWriteImage(queue: CommandQueue,
+                               image: Mem,
+                               blocking: u32,
+                               origin: *const size_t,
+                               region: *const size_t,
+                               row_pitch: size_t,
+                               slice_pitch: size_t,
+                               host: *const c_void,
+                               num_events: u32,
+                               events: *const Event,
+                               event: *mut Event) -> i32;
+
+    pub fn clEnqueueCopyImage(queue: CommandQueue,
+                              src_image: Mem,
+                              dst_image: Mem,
+                              src_origin: *const size_t,
+                              dst_origin: *const size_t,
+                              region: *const size_t,
+                              num_events: u32,
+                              events: *const Event,
+                              event: *mut Event) -> i32;
+
+    pub fn clEnqueueFillImage(queue: CommandQueue,
+                              image: Mem,
+                              fill_color: *const c_void,
+                              origin: *const size_t,
+                              region: *const size_t,
+                              num_events: u32,
+                              events: *const Event,
+                              event: *mut Event) -> i32;
+
+    pub fn clEnqueueMarker(queue: CommandQueue,
+                           event: *mut Event) -> i32;
+
+    pub fn clEnqueueMarkerWithWaitList(queue: CommandQueue,
+                                       num_events: u32,
+                                       events: *const Event,
+                                       event: *mut Event) -> i32;
+
+    pub fn clEnqueueBarrier(queue: CommandQueue) -> i32;
+
+    pub fn clEnqueueBarrierWithWaitList(queue: CommandQueue,
+                                        num_events: u32,
+                                        events: *const Event,
+                                        event: *mut Event) -> i32;
+
+    pub fn clEnqueueWaitForEvents(queue: CommandQueue,
+                                  num_events: u32,
+                                  events: *const Event) -> i32;
+
     pub fn clEnqueueCopyImageToBuffer(queue: CommandQueue,
                                       src_image: Mem,
                                       dst_buffer: Mem,
@@ -1,16 +1,213 @@
+extern crate libc;
+use self::libc::*;
+
 use low_level as ll;
 use error::Error;
+use context::Context;
+use kernel::Kernel;
+use command_queue::{CommandQueue, ReadLock, WriteLock, MapMode, MappedBuffer};
+use event::EventLike;
+
+use std::ptr;
+use std::mem::{size_of, transmute, zeroed};
+use std::marker::PhantomData;
+use std::ops::{BitOr, BitOrAssign, Deref};
+use std::sync::mpsc::{channel, Sender, Receiver};
 
 /// OpenCL memory object
 pub struct Mem {
     pub id: ll::Mem,
+    parent: Option<Box<Mem>>,
+}
+
+/// Flags controlling how a memory object is allocated and accessed
+///
+/// Flags combine with `|`, e.g., `MemFlags::READ_ONLY | MemFlags::ALLOC_HOST_PTR`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemFlags {
+    pub bits: u64,
+}
+
+/// Kind of OpenCL memory object
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemObjectType {
+    Buffer,
+    Image2D,
+    Image3D,
+    Image2DArray,
+    Image1D,
+    Image1DArray,
+    Image1DBuffer,
+    Pipe,
+    Other(u32),
+}
+
+/// Builder for buffers with non-default memory flags
+///
+/// Use `build()` for plain device allocations, `build_copying()` to
+/// initialize the buffer from a host slice, and `build_using()` to have
+/// OpenCL use a host slice as the buffer's storage.
+pub struct BufferBuilder {
+    context: Context,
+    flags: MemFlags,
+    size_bytes: usize,
+}
+
+/// Buffer backed by host memory borrowed for the lifetime `'a`
+///
+/// Returned by `BufferBuilder::build_using()`.  The host slice stays
+/// borrowed until this is dropped.  The underlying `Mem` is never handed
+/// out, since a cloned handle could outlive the slice; bind and transfer
+/// through the methods here instead.
+///
+/// Dropping blocks until OpenCL has destroyed the buffer, i.e., until
+/// every command using it has finished.
+pub struct HostMem<'a> {
+    // dropped before `released`, which then waits for the destructor
+    mem: Mem,
+    #[allow(dead_code)]
+    released: MemReleased,
+    marker: PhantomData<&'a mut [u8]>,
+}
+
+/// Kernel with a `HostMem` bound to one of its arguments
+///
+/// Returned by `HostMem::bind_to()`.  Dereferences to the kernel, so it
+/// can be passed to `CommandQueue::run()`.  The argument is cleared when
+/// this is dropped, so the kernel can't be run against the buffer once
+/// the host slice is released.
+pub struct HostMemArg<'b> {
+    kernel: &'b mut Kernel,
+    index: u32,
+    marker: PhantomData<&'b Mem>,
+}
+
+/// Blocks on drop until the destructor callback of a mem object has run
+struct MemReleased(Receiver<()>);
+
+extern fn mem_destroyed(_mem: ll::Mem, user_data: *mut c_void) -> () {
+    // takes back ownership of the sender leaked in `BufferBuilder::build_using()`
+    let sender: Box<Sender<()>> = unsafe { Box::from_raw(user_data as *mut Sender<()>) };
+    let _ = sender.send(());
 }
 
 impl Mem {
     pub fn new(id: ll::Mem) -> Mem {
         Mem{
             id: id,
+            parent: None,
+        }
+    }
+
+    fn get_info<T: Sized + Copy>(self: &Self, param: u32) -> Result<T, Error> {
+        let mut tr: T = unsafe { zeroed() };
+        unsafe {
+            try!(Error::check(ll::clGetMemObjectInfo(self.id,
+                                                     param,
+                                                     size_of::<T>() as size_t,
+                                                     transmute(&mut tr),
+                                                     ptr::null_mut())));
+        }
+        Ok(tr)
+    }
+
+    /// Returns the size of the memory object in bytes
+    pub fn size(self: &Self) -> Result<usize, Error> {
+        Ok(try!(self.get_info::<size_t>(0x1102)) as usize)
+    }
+
+    /// Returns whether the object is a buffer or one of the image types
+    pub fn mem_type(self: &Self) -> Result<MemObjectType, Error> {
+        let code: u32 = try!(self.get_info(0x1100));
+        Ok(MemObjectType::from_code(code))
+    }
+
+    /// Returns the flags the memory object was created with
+    pub fn flags(self: &Self) -> Result<MemFlags, Error> {
+        let bits: u64 = try!(self.get_info(0x1101));
+        Ok(MemFlags{ bits: bits })
+    }
+
+    /// Returns the host pointer given at creation, or null if there was none
+    pub fn host_ptr(self: &Self) -> Result<*mut c_void, Error> {
+        self.get_info(0x1103)
+    }
+
+    /// Returns the number of times the object is currently mapped
+    pub fn map_count(self: &Self) -> Result<u32, Error> {
+        self.get_info(0x1104)
+    }
+
+    /// Returns the OpenCL reference count; only useful for debugging
+    pub fn reference_count(self: &Self) -> Result<u32, Error> {
+        self.get_info(0x1105)
+    }
+
+    /// Returns the context the memory object was created in
+    pub fn context(self: &Self) -> Result<Context, Error> {
+        let id: ll::Context = try!(self.get_info(0x1106));
+        unsafe {
+            try!(Error::check(ll::clRetainContext(id)));
+        }
+        Ok(Context{id: id})
+    }
+
+    /// Returns the buffer this sub-buffer or image was created from, if any
+    pub fn associated_memobject(self: &Self) -> Result<Option<Mem>, Error> {
+        let id: ll::Mem = try!(self.get_info(0x1107));
+        if id.is_null() {
+            return Ok(None);
+        }
+        unsafe {
+            try!(Error::check(ll::clRetainMemObject(id)));
+        }
+        Ok(Some(Mem::new(id)))
+    }
+
+    /// Returns the offset in bytes of a sub-buffer within its parent
+    pub fn offset(self: &Self) -> Result<usize, Error> {
+        Ok(try!(self.get_info::<size_t>(0x1108)) as usize)
+    }
+
+    /// Create a sub-buffer covering `len` bytes starting at byte `offset`
+    ///
+    /// `offset` must be aligned to `mem_base_addr_align()` for at least one
+    /// device in the buffer's context, and the sub-buffer should only be
+    /// used on devices it is aligned for.  The sub-buffer keeps this buffer
+    /// alive.
+    pub fn sub_buffer(self: &Self,
+                      offset: usize,
+                      len: usize,
+                      flags: MemFlags) -> Result<Mem, Error> {
+        let devices = try!(try!(self.context()).devices());
+        let mut aligned = false;
+        for device in devices.iter() {
+            // mem_base_addr_align is in bits
+            let align = try!(device.mem_base_addr_align()) as usize / 8;
+            if align == 0 || offset % align == 0 {
+                aligned = true;
+                break;
+            }
         }
+        if !aligned {
+            return Err(Error::MisalignedSubBufferOffset);
+        }
+
+        let region = ll::BufferRegion{
+            origin: offset as size_t,
+            size: len as size_t,
+        };
+        let id = unsafe {
+            let mut err: i32 = 0;
+            let id = ll::clCreateSubBuffer(self.id,
+                                           flags.bits as c_ulong,
+                                           0x1220, // CL_BUFFER_CREATE_TYPE_REGION
+                                           transmute(&region),
+                                           &mut err);
+            try!(Error::check(err));
+            id
+        };
+        Ok(Mem{ id: id, parent: Some(Box::new(self.clone())) })
     }
 }
 
@@ -29,7 +226,261 @@ impl Clone for Mem {
             Error::check(ll::clRetainMemObject(self.id))
                 .ok().expect("error retaining mem object");
         }
-        Mem{ id: self.id }
+        Mem{ id: self.id, parent: self.parent.clone() }
     }
 }
 
+/// Anything that wraps an OpenCL memory object and can be bound to a kernel
+pub trait MemLike {
+    fn mem(self: &Self) -> &Mem;
+}
+
+impl MemLike for Mem {
+    fn mem(self: &Self) -> &Mem {
+        self
+    }
+}
+
+impl MemObjectType {
+    pub fn from_code(code: u32) -> MemObjectType {
+        match code {
+            0x10F0 => MemObjectType::Buffer,
+            0x10F1 => MemObjectType::Image2D,
+            0x10F2 => MemObjectType::Image3D,
+            0x10F3 => MemObjectType::Image2DArray,
+            0x10F4 => MemObjectType::Image1D,
+            0x10F5 => MemObjectType::Image1DArray,
+            0x10F6 => MemObjectType::Image1DBuffer,
+            0x10F7 => MemObjectType::Pipe,
+            _ => MemObjectType::Other(code),
+        }
+    }
+
+    pub fn code(self: &Self) -> u32 {
+        match *self {
+            MemObjectType::Buffer => 0x10F0,
+            MemObjectType::Image2D => 0x10F1,
+            MemObjectType::Image3D => 0x10F2,
+            MemObjectType::Image2DArray => 0x10F3,
+            MemObjectType::Image1D => 0x10F4,
+            MemObjectType::Image1DArray => 0x10F5,
+            MemObjectType::Image1DBuffer => 0x10F6,
+            MemObjectType::Pipe => 0x10F7,
+            MemObjectType::Other(code) => code,
+        }
+    }
+}
+
+impl MemFlags {
+    pub const READ_WRITE: MemFlags = MemFlags{ bits: 1 << 0 };
+    pub const WRITE_ONLY: MemFlags = MemFlags{ bits: 1 << 1 };
+    pub const READ_ONLY: MemFlags = MemFlags{ bits: 1 << 2 };
+    pub const USE_HOST_PTR: MemFlags = MemFlags{ bits: 1 << 3 };
+    pub const ALLOC_HOST_PTR: MemFlags = MemFlags{ bits: 1 << 4 };
+    pub const COPY_HOST_PTR: MemFlags = MemFlags{ bits: 1 << 5 };
+
+    /// No flags set; OpenCL treats this as `READ_WRITE`
+    pub fn empty() -> MemFlags {
+        MemFlags{ bits: 0 }
+    }
+
+    /// Returns true if every flag in `other` is also set in `self`
+    pub fn contains(self: &Self, other: MemFlags) -> bool {
+        self.bits & other.bits == other.bits
+    }
+}
+
+impl BitOr for MemFlags {
+    type Output = MemFlags;
+
+    fn bitor(self: Self, other: MemFlags) -> MemFlags {
+        MemFlags{ bits: self.bits | other.bits }
+    }
+}
+
+impl BitOrAssign for MemFlags {
+    fn bitor_assign(self: &mut Self, other: MemFlags) -> () {
+        self.bits |= other.bits;
+    }
+}
+
+impl BufferBuilder {
+    pub fn new(context: Context) -> BufferBuilder {
+        BufferBuilder{
+            context: context,
+            flags: MemFlags::empty(),
+            size_bytes: 0,
+        }
+    }
+
+    /// Sets the memory flags for the buffer
+    pub fn flags(self: Self, flags: MemFlags) -> BufferBuilder {
+        BufferBuilder{ flags: flags, .. self }
+    }
+
+    /// Sets the size of the buffer in bytes; ignored when building from a slice
+    pub fn size(self: Self, size_bytes: usize) -> BufferBuilder {
+        BufferBuilder{ size_bytes: size_bytes, .. self }
+    }
+
+    fn create(self: &Self,
+              flags: MemFlags,
+              size_bytes: usize,
+              host_ptr: *mut c_void) -> Result<Mem, Error> {
+        let id = unsafe {
+            let mut err: i32 = 0;
+            let id = ll::clCreateBuffer(self.context.id,
+                                        flags.bits as c_ulong,
+                                        size_bytes as size_t,
+                                        host_ptr,
+                                        &mut err);
+            try!(Error::check(err));
+            id
+        };
+        Ok(Mem::new(id))
+    }
+
+    /// Create a buffer without host memory
+    pub fn build(self: Self) -> Result<Mem, Error> {
+        self.create(self.flags, self.size_bytes, ptr::null_mut())
+    }
+
+    /// Create a buffer initialized with a copy of a slice (`COPY_HOST_PTR`)
+    pub fn build_copying<T: Sized>(self: Self,
+                                   slice: &[T]) -> Result<Mem, Error> {
+        let size_bytes = slice.len() * size_of::<T>();
+        self.create(self.flags | MemFlags::COPY_HOST_PTR,
+                    size_bytes,
+                    slice.as_ptr() as *mut c_void)
+    }
+
+    /// Create a buffer that uses a slice as its storage (`USE_HOST_PTR`)
+    ///
+    /// The slice is borrowed for as long as the returned `HostMem` lives.
+    pub fn build_using<'a, T: Sized>(self: Self,
+                                     slice: &'a mut [T]) -> Result<HostMem<'a>, Error> {
+        let size_bytes = slice.len() * size_of::<T>();
+        let mem = try!(self.create(self.flags | MemFlags::USE_HOST_PTR,
+                                   size_bytes,
+                                   slice.as_mut_ptr() as *mut c_void));
+        let (sender, receiver) = channel();
+        let user_data = Box::into_raw(Box::new(sender));
+        unsafe {
+            match Error::check(ll::clSetMemObjectDestructorCallback(mem.id,
+                                                                    mem_destroyed,
+                                                                    user_data as *mut c_void)) {
+                Ok(()) => {},
+                Err(e) => {
+                    // OpenCL never saw the callback, so free it here
+                    drop(Box::from_raw(user_data));
+                    return Err(e);
+                },
+            }
+        }
+        Ok(HostMem{ mem: mem, released: MemReleased(receiver), marker: PhantomData })
+    }
+}
+
+impl<'a> HostMem<'a> {
+    /// Returns the size of the buffer in bytes
+    pub fn size(self: &Self) -> Result<usize, Error> {
+        self.mem.size()
+    }
+
+    /// Binds the buffer to argument `index` of a kernel
+    ///
+    /// The binding lasts as long as the returned `HostMemArg`, which
+    /// borrows both the buffer and the kernel.
+    pub fn bind_to<'b>(self: &'b Self,
+                       kernel: &'b mut Kernel,
+                       index: u32) -> Result<HostMemArg<'b>, Error> {
+        try!(kernel.bind(index, &self.mem));
+        Ok(HostMemArg{ kernel: kernel, index: index, marker: PhantomData })
+    }
+
+    /// Writes a slice to the start of the buffer
+    ///
+    /// The returned lock borrows the buffer, so it can't be dropped while
+    /// the write is pending.
+    pub fn write<'b, T: Sized>(self: &'b mut Self,
+                               queue: &CommandQueue,
+                               slice: &'b [T],
+                               wait_for: &[&dyn EventLike]) -> Result<ReadLock<'b, T>, Error> {
+        queue.write_buffer_at(&mut self.mem, 0, slice, wait_for)
+    }
+
+    /// Reads from the start of the buffer into a slice
+    pub fn read<'b, T: Sized>(self: &'b Self,
+                              queue: &CommandQueue,
+                              slice: &'b mut [T],
+                              wait_for: &[&dyn EventLike]) -> Result<WriteLock<'b, T>, Error> {
+        queue.read_buffer_at(&self.mem, 0, slice, wait_for)
+    }
+
+    /// Maps the first `len` elements of the buffer into host memory
+    pub fn map<'b, T: Sized, M: MapMode>(self: &'b mut Self,
+                                         queue: &'b CommandQueue,
+                                         mode: M,
+                                         len: usize,
+                                         wait_for: &[&dyn EventLike]) -> Result<MappedBuffer<'b, T, M>, Error> {
+        queue.map_buffer(&mut self.mem, mode, len, wait_for)
+    }
+}
+
+impl<'b> Deref for HostMemArg<'b> {
+    type Target = Kernel;
+
+    fn deref(self: &Self) -> &Kernel {
+        self.kernel
+    }
+}
+
+impl<'b> Drop for HostMemArg<'b> {
+    fn drop(self: &mut Self) -> () {
+        // a null buffer argument is allowed; nothing to report an error to
+        let null: ll::Mem = ptr::null_mut();
+        unsafe {
+            ll::clSetKernelArg(self.kernel.id,
+                               self.index,
+                               size_of::<ll::Mem>() as size_t,
+                               transmute(&null));
+        }
+    }
+}
+
+impl Drop for MemReleased {
+    fn drop(self: &mut Self) -> () {
+        // errors only if the callback was dropped without running
+        let _ = self.0.recv();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MemFlags, MemObjectType};
+
+    #[test]
+    fn flags_combine() {
+        let flags = MemFlags::READ_ONLY | MemFlags::USE_HOST_PTR;
+        assert_eq!(flags.bits, (1 << 2) | (1 << 3));
+        assert!(flags.contains(MemFlags::READ_ONLY));
+        assert!(flags.contains(MemFlags::READ_ONLY | MemFlags::USE_HOST_PTR));
+        assert!(!flags.contains(MemFlags::WRITE_ONLY));
+    }
+
+    #[test]
+    fn flags_or_assign() {
+        let mut flags = MemFlags::empty();
+        assert!(flags.contains(MemFlags::empty()));
+        flags |= MemFlags::ALLOC_HOST_PTR;
+        assert_eq!(flags, MemFlags::ALLOC_HOST_PTR);
+    }
+
+    #[test]
+    fn mem_object_type_codes_round_trip() {
+        for &code in [0x10F0, 0x10F1, 0x10F7, 0x4000].iter() {
+            assert_eq!(MemObjectType::from_code(code).code(), code);
+        }
+        assert_eq!(MemObjectType::from_code(0x4000), MemObjectType::Other(0x4000));
+    }
+}
@@ -0,0 +1,171 @@
+use error::Error;
+
+/// Index space for a kernel launch
+///
+/// Create a range with `new_1d()`, `new_2d()` or `new_3d()`.  Without a
+/// local size, the driver picks the work-group size.  With `padded()`, the
+/// global size is rounded up to a multiple of the local size; kernels
+/// launched that way must bounds-check `get_global_id()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NDRange {
+    global: Vec<usize>,
+    offset: Option<Vec<usize>>,
+    local: Option<Vec<usize>>,
+    padded: bool,
+}
+
+/// Rounds `global` up to a multiple of `local`, or `None` on overflow
+fn next_mul(global: usize, local: usize) -> Option<usize> {
+    if global % local == 0 {
+        Some(global)
+    } else {
+        (global/local + 1).checked_mul(local)
+    }
+}
+
+impl NDRange {
+    pub fn new_1d(global: usize) -> NDRange {
+        NDRange::from_global(vec!(global))
+    }
+
+    pub fn new_2d(global: (usize, usize)) -> NDRange {
+        NDRange::from_global(vec!(global.0, global.1))
+    }
+
+    pub fn new_3d(global: (usize, usize, usize)) -> NDRange {
+        NDRange::from_global(vec!(global.0, global.1, global.2))
+    }
+
+    fn from_global(global: Vec<usize>) -> NDRange {
+        NDRange{
+            global: global,
+            offset: None,
+            local: None,
+            padded: false,
+        }
+    }
+
+    /// Sets the global work offset; needs one entry per dimension
+    pub fn offset(self: Self, offset: &[usize]) -> NDRange {
+        NDRange{ offset: Some(offset.to_vec()), .. self }
+    }
+
+    /// Sets the local work size; needs one entry per dimension
+    pub fn local(self: Self, local: &[usize]) -> NDRange {
+        NDRange{ local: Some(local.to_vec()), .. self }
+    }
+
+    /// Rounds the global size up to a multiple of the local size, which
+    /// must be set with `local()`
+    pub fn padded(self: Self) -> NDRange {
+        NDRange{ padded: true, .. self }
+    }
+
+    /// Returns the number of dimensions, from 1 to 3
+    pub fn dimensions(self: &Self) -> u32 {
+        self.global.len() as u32
+    }
+
+    /// Returns the global size the kernel is launched with, after padding
+    ///
+    /// Only meaningful once `check()` has passed.
+    pub fn global_size(self: &Self) -> Vec<usize> {
+        match (self.padded, &self.local) {
+            (true, &Some(ref local)) => self.global.iter().zip(local.iter())
+                .map(|(&g, &l)| next_mul(g, l).unwrap_or(g))
+                .collect(),
+            _ => self.global.clone(),
+        }
+    }
+
+    pub fn global_offset(self: &Self) -> Option<&[usize]> {
+        self.offset.as_ref().map(|o| &o[..])
+    }
+
+    pub fn local_size(self: &Self) -> Option<&[usize]> {
+        self.local.as_ref().map(|l| &l[..])
+    }
+
+    /// Checks that offset and local sizes match the dimensionality, and
+    /// that padding has a local size to pad to
+    pub fn check(self: &Self) -> Result<(), Error> {
+        let dims = self.global.len();
+        if let Some(ref offset) = self.offset {
+            if offset.len() != dims {
+                return Err(Error::InvalidWorkDimension);
+            }
+        }
+        if let Some(ref local) = self.local {
+            if local.len() != dims {
+                return Err(Error::InvalidWorkDimension);
+            }
+            if local.iter().any(|&l| l == 0) {
+                return Err(Error::InvalidWorkGroupSize);
+            }
+            if self.padded && self.global.iter().zip(local.iter())
+                    .any(|(&g, &l)| next_mul(g, l).is_none()) {
+                return Err(Error::InvalidGlobalWorkSize);
+            }
+        } else if self.padded {
+            return Err(Error::InvalidWorkGroupSize);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use error::Error;
+
+    #[test]
+    fn global_size_is_unpadded_by_default() {
+        let range = NDRange::new_2d((10, 7)).local(&[4, 4]);
+        assert_eq!(range.global_size(), vec!(10, 7));
+        assert_eq!(range.dimensions(), 2);
+    }
+
+    #[test]
+    fn padded_rounds_up_to_local_size() {
+        let range = NDRange::new_3d((10, 8, 1)).local(&[4, 4, 1]).padded();
+        assert!(range.check().is_ok());
+        assert_eq!(range.global_size(), vec!(12, 8, 1));
+    }
+
+    #[test]
+    fn padded_without_local_is_rejected() {
+        match NDRange::new_1d(10).padded().check() {
+            Err(Error::InvalidWorkGroupSize) => {},
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn padding_overflow_is_rejected() {
+        let range = NDRange::new_1d(usize::max_value()).local(&[2]).padded();
+        match range.check() {
+            Err(Error::InvalidGlobalWorkSize) => {},
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn mismatched_dimensions_are_rejected() {
+        match NDRange::new_2d((4, 4)).offset(&[1]).check() {
+            Err(Error::InvalidWorkDimension) => {},
+            other => panic!("unexpected {:?}", other),
+        }
+        match NDRange::new_1d(4).local(&[1, 1]).check() {
+            Err(Error::InvalidWorkDimension) => {},
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn zero_local_size_is_rejected() {
+        match NDRange::new_1d(4).local(&[0]).check() {
+            Err(Error::InvalidWorkGroupSize) => {},
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
@@ -50,13 +50,22 @@ impl Program {
 
     pub fn build(self: Self,
                 devices: &[Device]) -> Result<BuiltProgram, Error> {
+        self.build_with_options(devices, "")
+    }
+
+    /// Build with compiler options, e.g., `-cl-kernel-arg-info`
+    pub fn build_with_options<T: AsRef<str>>(self: Self,
+                                             devices: &[Device],
+                                             options: T) -> Result<BuiltProgram, Error> {
+        let options_str = CString::new(options.as_ref())
+                .ok().expect("build options are invalid c string");
         let num_devices = devices.len() as u32;
         let device_ids: Vec<ll::DeviceID> = devices.iter().map(|d| d.id).collect();
         unsafe {
             match Error::check(ll::clBuildProgram(self.id,
                                                   num_devices,
                                                   &device_ids[0],
-                                                  ptr::null(),
+                                                  options_str.as_ptr() as *const u8,
                                                   ptr::null(),
                                                   ptr::null_mut())) {
                 Ok(()) => {},
@@ -0,0 +1,89 @@
+use low_level as ll;
+use error::Error;
+use context::Context;
+
+/// How out-of-range image coordinates are handled by a sampler
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressingMode {
+    None,
+    ClampToEdge,
+    Clamp,
+    Repeat,
+    MirroredRepeat,
+}
+
+/// How a sampler filters between image elements
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterMode {
+    Nearest,
+    Linear,
+}
+
+/// OpenCL sampler
+///
+/// Bind a sampler to a kernel's `sampler_t` argument with
+/// `Kernel::bind_sampler()`.
+pub struct Sampler {
+    pub id: ll::Sampler,
+}
+
+impl AddressingMode {
+    pub fn code(self: &Self) -> u32 {
+        match *self {
+            AddressingMode::None => 0x1130,
+            AddressingMode::ClampToEdge => 0x1131,
+            AddressingMode::Clamp => 0x1132,
+            AddressingMode::Repeat => 0x1133,
+            AddressingMode::MirroredRepeat => 0x1134,
+        }
+    }
+}
+
+impl FilterMode {
+    pub fn code(self: &Self) -> u32 {
+        match *self {
+            FilterMode::Nearest => 0x1140,
+            FilterMode::Linear => 0x1141,
+        }
+    }
+}
+
+impl Sampler {
+    pub fn new(context: Context,
+               normalized_coords: bool,
+               addressing_mode: AddressingMode,
+               filter_mode: FilterMode) -> Result<Sampler, Error> {
+        let id = unsafe {
+            let mut err: i32 = 0;
+            let id = ll::clCreateSampler(context.id,
+                                         if normalized_coords { 1 } else { 0 },
+                                         addressing_mode.code(),
+                                         filter_mode.code(),
+                                         &mut err);
+            try!(Error::check(err));
+            id
+        };
+        Ok(Sampler{ id: id })
+    }
+}
+
+impl Drop for Sampler {
+    fn drop(self: &mut Self) -> () {
+        unsafe {
+            Error::check(ll::clReleaseSampler(self.id))
+                .ok().expect("error releasing sampler");
+        }
+    }
+}
+
+impl Clone for Sampler {
+    fn clone(self: &Self) -> Self {
+        unsafe {
+            Error::check(ll::clRetainSampler(self.id))
+                .ok().expect("error retaining sampler");
+        }
+        Sampler{ id: self.id }
+    }
+}
+
+unsafe impl Send for Sampler { }